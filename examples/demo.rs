@@ -1,6 +1,6 @@
 use std::{error::Error, fmt::Display};
 
-use rgb_log::{Log, debug, error};
+use rgb_log::{Log, debug, error, info};
 
 const SUBMODULE_NAMES: [&str; 2] = ["very_long_subject", "main"];
 
@@ -12,9 +12,14 @@ struct Displayable(&'static str);
 struct Debugable(&'static str);
 
 fn main() {
-    let log = Log::builder().with_submodule_names(SUBMODULE_NAMES).build();
+    // e.g. RGB_LOG=info,very_long_subject=debug
+    let log = Log::builder()
+        .with_submodule_names(SUBMODULE_NAMES)
+        .with_env_filter(None)
+        .build();
 
     log.info("42");
+    info!(log, user_id = 42, name = "ferris"; "login from {}", "127.0.0.1");
 
     if let Err(err) = a_submodule::erroring_fn(log.submodule("very_long_subject")) {
         error!(log, "submodule error: {}", err);
@@ -0,0 +1,21 @@
+use rgb_log::{Log, log_ext};
+
+fn main() {
+    let log = Log::builder()
+        .with_submodule_names(["log_facade", "log_facade::dependency"])
+        .build();
+
+    log_ext::init(log.clone()).expect("no other logger is installed");
+
+    log.info("records of the log crate end up here too");
+
+    ::log::info!("hello from the facade");
+    ::log::warn!(target: "log_facade::dependency", "warnings are printed as ERROR by default");
+    dependency::work();
+}
+
+mod dependency {
+    pub fn work() {
+        ::log::debug!("the module path is used as the submodule");
+    }
+}
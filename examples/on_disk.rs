@@ -0,0 +1,24 @@
+use rgb_log::{
+    Log,
+    buf_ext::{OnDisk, Rotation},
+};
+
+#[tokio::main(flavor = "current_thread")]
+async fn main() -> Result<(), std::io::Error> {
+    let on_disk = OnDisk::builder("on_disk_example.log")
+        .with_rotation(Rotation::Daily)
+        .with_retention(7)
+        .build()
+        .await?;
+
+    let log = Log::builder().with_buffer(on_disk).build();
+
+    log.info("written to the terminal and on_disk_example.log");
+    log.submodule("main")
+        .ok("lines reach the file in the order they were logged");
+
+    // Lines are batched, wait for them before exiting
+    log.shutdown().await;
+
+    Ok(())
+}
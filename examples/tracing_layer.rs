@@ -0,0 +1,19 @@
+use rgb_log::{Log, tracing_ext::LogLayer};
+use tracing_subscriber::{Registry, layer::SubscriberExt};
+
+fn main() {
+    let log = Log::builder()
+        .with_submodule_names(["tracing_layer", "startup"])
+        .build();
+
+    let subscriber = Registry::default().with(LogLayer::new(log));
+    tracing::subscriber::set_global_default(subscriber).expect("no other subscriber is set");
+
+    tracing::info!("outside of any span the target is used");
+
+    let span = tracing::info_span!("startup");
+    let _enter = span.enter();
+
+    tracing::debug!(attempt = 1, "inside a span its name is used");
+    tracing::warn!("warnings are printed as ERROR by default");
+}
@@ -1,97 +1,240 @@
 use std::sync::Arc;
 use tokio::sync::RwLock;
 
-use crate::log::LogBuffer;
+use crate::log::{FlushFuture, LogBuffer};
 
 pub use in_memory::InMemory;
-pub use on_disk::OnDisk;
-pub use smart::Smart;
+pub use on_disk::{Compression, OnDisk, Rotation};
+pub use ring::{Capacity, Ring};
+pub use smart::{ShutdownHandle, Smart};
 
-mod on_disk {
-    use super::*;
+mod on_disk;
 
-    use std::path::Path;
+mod in_memory {
+    use super::*;
 
-    use tokio::{
-        fs::File,
-        io::{AsyncWriteExt, BufStream},
-        sync::Mutex,
-    };
+    use std::sync::{RwLockReadGuard, RwLockWriteGuard};
 
+    /// Keeps every line in memory.
+    /// Uses a blocking lock held only briefly, so it can be read from sync and async code alike.
     #[derive(Clone)]
-    pub struct OnDisk {
-        cursor: Arc<Mutex<BufStream<File>>>,
+    pub struct InMemory {
+        inner: Arc<std::sync::RwLock<Vec<String>>>,
     }
 
-    impl OnDisk {
-        pub async fn new(p: impl AsRef<Path>) -> Result<Self, std::io::Error> {
-            let f = File::options()
-                .append(true)
-                .create(true)
-                .read(true)
-                .open(p)
-                .await?;
+    impl InMemory {
+        #[allow(clippy::new_without_default)]
+        pub fn new() -> Self {
+            Self {
+                inner: Arc::new(std::sync::RwLock::new(vec![])),
+            }
+        }
 
-            let cursor = BufStream::new(f);
+        pub async fn push(&self, value: String) {
+            self.write().push(value);
+        }
 
-            Ok(Self {
-                cursor: Arc::new(Mutex::new(cursor)),
-            })
+        /// Copy of all lines
+        pub fn lines(&self) -> Vec<String> {
+            self.read().clone()
+        }
+
+        /// Copy of the last n lines, or fewer if there are not as many
+        pub fn last(&self, n: usize) -> Vec<String> {
+            let lock = self.read();
+            lock[lock.len().saturating_sub(n)..].to_vec()
+        }
+
+        /// Copy of the lines from index onwards. Useful for polling with the previous len.
+        pub fn since(&self, index: usize) -> Vec<String> {
+            let lock = self.read();
+            lock.get(index..)
+                .map(<[String]>::to_vec)
+                .unwrap_or_default()
+        }
+
+        pub fn len(&self) -> usize {
+            self.read().len()
         }
 
-        async fn push(&self, line: String) {
-            let buffer = line.as_ref();
+        pub fn is_empty(&self) -> bool {
+            self.read().is_empty()
+        }
+
+        pub fn clear(&self) {
+            self.write().clear();
+        }
 
-            let mut lock = self.cursor.lock().await;
+        fn read(&self) -> RwLockReadGuard<'_, Vec<String>> {
+            self.inner.read().unwrap_or_else(|err| err.into_inner())
+        }
 
-            let _ = lock
-                .write_all(buffer)
-                .await
-                .inspect_err(|err| eprintln!("rgb_log: ERROR WRITING OnDisk LOG: {err}"));
-            let _ = lock
-                .flush()
-                .await
-                .inspect_err(|err| eprintln!("rgb_log: ERROR FLUSHING OnDisk LOG: {err}"));
+        fn write(&self) -> RwLockWriteGuard<'_, Vec<String>> {
+            self.inner.write().unwrap_or_else(|err| err.into_inner())
         }
     }
 
-    impl LogBuffer for OnDisk {
+    impl LogBuffer for InMemory {
         fn push_line(&self, line: String) {
-            let buffer = self.clone();
-            tokio::spawn(async move { buffer.push(line.to_string()).await });
+            self.write().push(line);
         }
     }
+
+    #[test]
+    fn queries_lines_pushed_by_log() {
+        let memory = InMemory::new();
+        let log = crate::Log::builder()
+            .with_buffer(memory.clone())
+            .without_console()
+            .build();
+
+        log.info("first");
+        log.info("second");
+
+        assert_eq!(memory.len(), 2);
+        assert_eq!(memory.last(5), memory.lines());
+        assert!(memory.last(1)[0].ends_with("INFO: second\r\n"));
+
+        let seen = memory.len();
+        assert!(memory.since(seen).is_empty());
+        log.error("third");
+        assert_eq!(memory.since(seen).len(), 1);
+        assert!(memory.since(seen + 5).is_empty());
+
+        memory.clear();
+        assert!(memory.is_empty());
+        assert!(memory.last(1).is_empty());
+    }
 }
 
-mod in_memory {
+mod ring {
     use super::*;
 
+    use std::{collections::VecDeque, sync::MutexGuard};
+
+    #[derive(Clone, Copy, Debug)]
+    pub enum Capacity {
+        Lines(usize),
+        /// Sum of the byte lengths of the retained lines
+        Bytes(usize),
+    }
+
+    /// Keeps the most recent lines within a fixed capacity, evicting the oldest ones
     #[derive(Clone)]
-    pub struct InMemory {
-        inner: Arc<RwLock<Vec<String>>>,
+    pub struct Ring {
+        inner: Arc<std::sync::Mutex<State>>,
     }
 
-    impl InMemory {
-        #[allow(clippy::new_without_default)]
-        pub fn new() -> Self {
+    struct State {
+        lines: VecDeque<String>,
+        bytes: usize,
+        capacity: Capacity,
+        dropped: u64,
+    }
+
+    impl State {
+        fn is_full(&self) -> bool {
+            match self.capacity {
+                Capacity::Lines(n) => self.lines.len() > n,
+                Capacity::Bytes(n) => self.bytes > n,
+            }
+        }
+    }
+
+    impl Ring {
+        pub fn new(capacity: Capacity) -> Self {
+            let lines = match capacity {
+                Capacity::Lines(n) => VecDeque::with_capacity(n),
+                Capacity::Bytes(_) => VecDeque::new(),
+            };
+
+            let state = State {
+                lines,
+                bytes: 0,
+                capacity,
+                dropped: 0,
+            };
+
             Self {
-                inner: Arc::new(RwLock::new(vec![])),
+                inner: Arc::new(std::sync::Mutex::new(state)),
             }
         }
 
-        pub async fn push(&self, value: String) {
-            self.inner.write().await.push(value);
+        /// Copy of the retained lines, oldest first
+        pub fn lines(&self) -> Vec<String> {
+            self.lock().lines.iter().cloned().collect()
+        }
+
+        /// Takes the retained lines, oldest first, leaving the buffer empty.
+        /// The dropped count is kept.
+        pub fn drain(&self) -> Vec<String> {
+            let mut lock = self.lock();
+            lock.bytes = 0;
+            lock.lines.drain(..).collect()
+        }
+
+        /// Number of lines evicted so far
+        pub fn dropped(&self) -> u64 {
+            self.lock().dropped
+        }
+
+        pub fn len(&self) -> usize {
+            self.lock().lines.len()
+        }
+
+        pub fn is_empty(&self) -> bool {
+            self.lock().lines.is_empty()
+        }
+
+        fn lock(&self) -> MutexGuard<'_, State> {
+            self.inner.lock().unwrap_or_else(|err| err.into_inner())
         }
     }
 
-    //impl LogBuffer for InMemory {
-    //    async fn push_line<S: Sized + AsRef<str> + ToString>(&self, line: S) {
-    //        self.push(line.to_string()).await;
-    //    }
-    //}
+    impl LogBuffer for Ring {
+        fn push_line(&self, line: String) {
+            let mut lock = self.lock();
+
+            lock.bytes += line.len();
+            lock.lines.push_back(line);
+
+            // A line larger than the whole capacity evicts itself as well
+            while lock.is_full() {
+                let Some(evicted) = lock.lines.pop_front() else {
+                    break;
+                };
+                lock.bytes -= evicted.len();
+                lock.dropped += 1;
+            }
+        }
+    }
+
+    #[test]
+    fn evicts_oldest() {
+        let ring = Ring::new(Capacity::Bytes(6));
+
+        for line in ["ab", "cd", "ef", "gh"] {
+            ring.push_line(line.to_string());
+        }
+        assert_eq!(ring.lines(), ["cd", "ef", "gh"]);
+
+        ring.push_line("too long".to_string());
+        assert_eq!(ring.dropped(), 5);
+        assert!(ring.drain().is_empty());
+
+        let ring = Ring::new(Capacity::Lines(2));
+
+        for line in ["a", "b", "c"] {
+            ring.push_line(line.to_string());
+        }
+        assert_eq!(ring.drain(), ["b", "c"]);
+        assert!(ring.is_empty());
+
+        // Draining is not dropping
+        assert_eq!(ring.dropped(), 1);
+    }
 }
 
-#[allow(unused)]
 mod smart {
     use super::*;
 
@@ -103,69 +246,121 @@ mod smart {
     use tokio::{
         fs::File,
         io::{AsyncBufReadExt, AsyncWriteExt, BufReader},
-        sync::mpsc::{UnboundedReceiver, UnboundedSender, unbounded_channel},
+        sync::{
+            Mutex, RwLockWriteGuard,
+            mpsc::{UnboundedReceiver, UnboundedSender, unbounded_channel},
+            oneshot,
+        },
+        task::JoinHandle,
+        time::Instant,
     };
 
     /// For lack of a better name...
     /// Keeps an in memory buffer, and offloads to disk when it hasn't been accessed for X amount
-    /// of time.
+    /// of time. Reading the lines loads them back into memory.
+    ///
+    /// Lines are kept without their line ending.
     #[derive(Clone)]
     pub struct Smart {
         state: Arc<RwLock<State>>,
+        path: Arc<PathBuf>,
         loader: Loader,
     }
 
     impl Smart {
+        /// Spawns the loader task, so it must be called within a tokio runtime.
+        /// in_memory_timeout defaults to 30 minutes.
         pub fn new(log_path: impl Into<PathBuf>, in_memory_timeout: Option<Duration>) -> Self {
             let state = State {
                 inner: vec![],
                 status: Status::InMemory,
             };
             let state = Arc::new(RwLock::new(state));
+            let path = Arc::new(log_path.into());
 
             let loader = Loader::new(
                 state.clone(),
                 in_memory_timeout.unwrap_or(Duration::from_secs(60 * 30)),
-                log_path.into(),
+                path.clone(),
             );
 
-            Smart { state, loader }
+            Smart {
+                state,
+                path,
+                loader,
+            }
         }
 
+        /// Queued behind lines given to push_line, resolves once the line is pushed
         pub async fn push(&self, value: String) {
-            let lock = self.state.read().await;
-
-            todo!();
-
-            if let Status::OnDisk = lock.status {
-                drop(lock);
+            self.push_line(value);
+            self.flush().await;
+        }
 
-                // !!!!!! todo: wait for a message from loader when loaded
-                // this is all dumb. what if a reader hops in between when the memory buffer is
-                // still empty!?
-                self.load();
-            } else {
-                drop(lock);
-            }
+        /// Copy of all lines pushed so far, loading offloaded ones back into memory first.
+        /// Other readers and writers wait until loading is done.
+        pub async fn lines(&self) -> Vec<String> {
+            // Lines may still be queued for the loader task
+            self.flush().await;
 
-            let mut lock = self.state.write().await;
-            lock.inner.push(value);
+            let lock = Loader::load(&self.state, self.path.as_ref()).await;
+            let lines = lock.inner.clone();
             drop(lock);
 
             self.extend_timeout();
+            lines
         }
 
-        fn load(&self) {
-            self.loader.tx.send(Msg::Load).unwrap();
+        pub fn shutdown_handle(&self) -> ShutdownHandle {
+            self.loader.shutdown.clone()
         }
 
         fn extend_timeout(&self) {
-            self.loader.tx.send(Msg::ExtendTimeout).unwrap();
+            let _ = self.loader.tx.send(Msg::ExtendTimeout);
+        }
+    }
+
+    impl LogBuffer for Smart {
+        /// Pushed in order by the loader task. Lines pushed after a shutdown are dropped.
+        fn push_line(&self, line: String) {
+            let _ = self.loader.tx.send(Msg::Push(line));
+        }
+
+        /// Waits for the loader task to push the lines sent to it so far
+        fn flush(&self) -> FlushFuture<'_> {
+            Box::pin(async {
+                let (tx, rx) = oneshot::channel();
+                if self.loader.tx.send(Msg::Flush(tx)).is_ok() {
+                    let _ = rx.await;
+                }
+            })
+        }
+    }
+
+    /// Stops the loader task of a Smart buffer
+    #[derive(Clone)]
+    pub struct ShutdownHandle {
+        tx: UnboundedSender<Msg>,
+        handle: Arc<Mutex<Option<JoinHandle<()>>>>,
+    }
+
+    impl ShutdownHandle {
+        /// Waits for lines already pushed, then offloads them all to disk.
+        /// Reading the lines of the Smart buffer still works afterwards.
+        pub async fn stop(&self) {
+            let _ = self.tx.send(Msg::Stop);
+
+            if let Some(handle) = self.handle.lock().await.take() {
+                let _ = handle
+                    .await
+                    .inspect_err(|err| eprintln!("rgb_log: Smart LOADER TASK FAILED: {err}"));
+            }
         }
     }
 
     enum Status {
         InMemory,
+        /// The file holds the lines preceding those in memory
         OnDisk,
     }
 
@@ -174,71 +369,92 @@ mod smart {
         pub status: Status,
     }
 
-    //
     enum Msg {
-        Load,
+        Push(String),
+        Flush(oneshot::Sender<()>),
         ExtendTimeout,
         Stop,
     }
 
     #[derive(Clone)]
     struct Loader {
-        state: Arc<RwLock<State>>,
         tx: UnboundedSender<Msg>,
+        shutdown: ShutdownHandle,
     }
 
     impl Loader {
-        pub fn new(state: Arc<RwLock<State>>, timeout: Duration, path: PathBuf) -> Self {
+        pub fn new(state: Arc<RwLock<State>>, timeout: Duration, path: Arc<PathBuf>) -> Self {
             let (tx, rx) = unbounded_channel();
 
-            let _handle = Self::main_loop(state.clone(), rx, timeout, path);
+            let handle = Self::main_loop(state, rx, timeout, path);
+            let shutdown = ShutdownHandle {
+                tx: tx.clone(),
+                handle: Arc::new(Mutex::new(Some(handle))),
+            };
 
-            Self { state, tx }
+            Self { tx, shutdown }
         }
 
         fn main_loop(
             state: Arc<RwLock<State>>,
             mut rx: UnboundedReceiver<Msg>,
             timeout: Duration,
-            path: PathBuf,
-        ) -> tokio::task::JoinHandle<()> {
+            path: Arc<PathBuf>,
+        ) -> JoinHandle<()> {
             tokio::spawn(async move {
-                let timeout = async || {
-                    tokio::time::sleep(timeout).await;
-                };
+                let sleep = tokio::time::sleep(timeout);
+                tokio::pin!(sleep);
+                // Disarmed while nothing is left in memory
+                let mut armed = true;
 
                 loop {
                     tokio::select! {
-                        _ = timeout() => {
-                            Self::offload(state.clone(), &path).await;
+                        _ = &mut sleep, if armed => {
+                            Self::offload(&state, path.as_ref()).await;
+                            armed = false;
                         }
-                        Some(msg) = rx.recv() => {
+                        msg = rx.recv() => {
                             match msg {
-                                Msg::Load => Self::load(state.clone(), &path).await,
-                                Msg::ExtendTimeout => continue,
-                                Msg::Stop => break,
+                                Some(Msg::Push(line)) => Self::push(&state, line).await,
+                                Some(Msg::Flush(done)) => {
+                                    let _ = done.send(());
+                                }
+                                Some(Msg::ExtendTimeout) => (),
+                                Some(Msg::Stop) | None => {
+                                    Self::offload(&state, path.as_ref()).await;
+                                    break;
+                                }
                             }
+
+                            sleep.as_mut().reset(Instant::now() + timeout);
+                            armed = true;
                         }
                     }
                 }
             })
         }
 
-        async fn load(state: Arc<RwLock<State>>, p: impl AsRef<Path>) {
+        /// Pushing never waits for offloaded lines to be loaded
+        async fn push(state: &RwLock<State>, mut line: String) {
+            line.truncate(line.trim_end_matches(['\r', '\n']).len());
+            state.write().await.inner.push(line);
+        }
+
+        /// Returns the lock once all lines are in memory.
+        /// If reading the file fails, only the lines pushed since offloading are present.
+        async fn load(state: &RwLock<State>, p: impl AsRef<Path>) -> RwLockWriteGuard<'_, State> {
             let mut lock = state.write().await;
 
             if let Status::InMemory = lock.status {
-                eprintln!(
-                    "rgb_log: ATTEMPT TO OVERWRITE IN-MEMORY LOGS CANCELLED. LINES IN MEMORY: {}",
-                    lock.inner.len()
-                );
-                return;
+                return lock;
             }
 
             match File::options().read(true).open(p.as_ref()).await {
                 Ok(f) => match get_file_lines(f).await {
-                    Ok(vec) => {
+                    Ok(mut vec) => {
+                        vec.append(&mut lock.inner);
                         lock.inner = vec;
+                        lock.status = Status::InMemory;
                     }
                     Err(ioerr) => {
                         eprintln!(
@@ -254,31 +470,39 @@ mod smart {
                     );
                 }
             };
+
+            lock
         }
 
-        async fn offload(state: Arc<RwLock<State>>, p: impl AsRef<Path>) {
+        /// Overwrites the file with all lines when they are in memory,
+        /// otherwise appends the ones pushed since the last offload
+        async fn offload(state: &RwLock<State>, p: impl AsRef<Path>) {
             let mut lock = state.write().await;
 
-            match File::options()
-                .write(true)
-                .truncate(true)
-                .open(p.as_ref())
-                .await
-            {
+            let mut options = File::options();
+            match lock.status {
+                Status::InMemory => options.write(true).create(true).truncate(true),
+                Status::OnDisk => options.append(true).create(true),
+            };
+
+            match options.open(p.as_ref()).await {
                 Ok(mut f) => {
                     let offloaded = std::mem::take(&mut lock.inner);
 
                     let bytes = offloaded
-                        .clone()
-                        .into_iter()
-                        .map(|mut s| {
-                            s.push('\n');
-                            s.into_bytes()
-                        })
+                        .iter()
+                        .flat_map(|s| [s.as_bytes(), b"\n"])
                         .flatten()
+                        .copied()
                         .collect::<Vec<u8>>();
 
-                    match f.write_all(&bytes).await {
+                    // tokio only guarantees the bytes reached the file once flushed
+                    let written = match f.write_all(&bytes).await {
+                        Ok(_) => f.flush().await,
+                        Err(err) => Err(err),
+                    };
+
+                    match written {
                         Ok(_) => lock.status = Status::OnDisk,
                         Err(_) => {
                             lock.inner = offloaded;
@@ -311,4 +535,31 @@ mod smart {
 
         Ok(vec)
     }
+
+    #[tokio::test]
+    async fn offloads_and_reloads() {
+        let path = std::env::temp_dir().join(format!("rgb_log_smart_{}.log", std::process::id()));
+        let smart = Smart::new(&path, Some(Duration::from_millis(20)));
+
+        smart.push_line("first\r\n".to_string());
+        smart.push_line("second\r\n".to_string());
+
+        // Offloaded once the timeout passes without any access
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while std::fs::read_to_string(&path).unwrap_or_default() != "first\nsecond\n" {
+            assert!(Instant::now() < deadline, "lines were not offloaded");
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+
+        smart.push_line("third".to_string());
+        assert_eq!(smart.lines().await, ["first", "second", "third"]);
+
+        smart.shutdown_handle().stop().await;
+        assert_eq!(
+            std::fs::read_to_string(&path).unwrap(),
+            "first\nsecond\nthird\n"
+        );
+
+        let _ = std::fs::remove_file(path);
+    }
 }
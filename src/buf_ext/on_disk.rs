@@ -0,0 +1,921 @@
+use std::{
+    ffi::OsString,
+    io::ErrorKind,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use chrono::{DateTime, Utc};
+use tokio::{
+    fs::File,
+    io::{AsyncWriteExt, BufWriter},
+    sync::{
+        mpsc::{UnboundedReceiver, UnboundedSender, unbounded_channel},
+        oneshot,
+    },
+    task::JoinSet,
+    time::MissedTickBehavior,
+};
+
+use crate::log::{
+    FlushFuture, LogBuffer,
+    clock::{Clock, SystemClock},
+};
+
+pub use compression::Compression;
+
+mod compression;
+
+/// When OnDisk moves the current file aside and starts a new one
+#[derive(Clone, Copy, Default, Debug, PartialEq)]
+pub enum Rotation {
+    #[default]
+    Never,
+    /// Before a line would grow the file past this many bytes.
+    /// Rotated files are numbered, e.g. `app.log.1`, `app.log.2`, higher is newer.
+    Size(u64),
+    /// On the first line of a new UTC day, e.g. `app.log.2026-10-17`
+    Daily,
+    /// On the first line of a new UTC hour, e.g. `app.log.2026-10-17-13`
+    Hourly,
+}
+
+impl Rotation {
+    fn period(&self, time: DateTime<Utc>) -> Option<String> {
+        match self {
+            Self::Daily => Some(time.format("%Y-%m-%d").to_string()),
+            Self::Hourly => Some(time.format("%Y-%m-%d-%H").to_string()),
+            Self::Never | Self::Size(_) => None,
+        }
+    }
+}
+
+/// Appends lines to a file from a single writer task, keeping them in the order they were
+/// pushed. Lines are batched and written out every flush interval, or sooner once the batch
+/// size is reached.
+#[derive(Clone)]
+pub struct OnDisk {
+    tx: UnboundedSender<Msg>,
+}
+
+enum Msg {
+    Line(String),
+    Flush(oneshot::Sender<()>),
+}
+
+impl OnDisk {
+    pub async fn new(p: impl AsRef<Path>) -> Result<Self, std::io::Error> {
+        Self::builder(p.as_ref()).build().await
+    }
+
+    pub fn builder(p: impl Into<PathBuf>) -> Builder {
+        Builder {
+            path: p.into(),
+            rotation: Rotation::Never,
+            retention: Retention::default(),
+            compression: None,
+            flush_interval: Duration::from_secs(1),
+            batch_size: 64 * 1024,
+            clock: Box::new(SystemClock),
+        }
+    }
+
+    /// Waits until every line pushed so far is written and synced to the file,
+    /// and rotated files are done compressing
+    pub async fn flush(&self) {
+        let (tx, rx) = oneshot::channel();
+        if self.tx.send(Msg::Flush(tx)).is_ok() {
+            let _ = rx.await;
+        }
+    }
+
+    /// Like flush, blocking the thread instead. Panics when called from async code.
+    pub fn flush_blocking(&self) {
+        let (tx, rx) = oneshot::channel();
+        if self.tx.send(Msg::Flush(tx)).is_ok() {
+            let _ = rx.blocking_recv();
+        }
+    }
+}
+
+impl LogBuffer for OnDisk {
+    fn push_line(&self, line: String) {
+        let _ = self.tx.send(Msg::Line(line));
+    }
+
+    fn flush(&self) -> FlushFuture<'_> {
+        Box::pin(OnDisk::flush(self))
+    }
+}
+
+pub struct Builder {
+    path: PathBuf,
+    rotation: Rotation,
+    retention: Retention,
+    compression: Option<Compression>,
+    flush_interval: Duration,
+    batch_size: usize,
+    clock: Box<dyn Clock>,
+}
+
+impl Builder {
+    pub fn with_rotation(mut self, rotation: Rotation) -> Self {
+        self.rotation = rotation;
+        self
+    }
+
+    /// Number of rotated files to keep, the oldest are removed. All are kept by default.
+    pub fn with_retention(mut self, files: usize) -> Self {
+        let _ = self.retention.files.insert(files);
+        self
+    }
+
+    /// Total size of rotated files to keep, the oldest are removed. Unlimited by default.
+    /// Can be combined with with_retention.
+    pub fn with_size_budget(mut self, bytes: u64) -> Self {
+        let _ = self.retention.bytes.insert(bytes);
+        self
+    }
+
+    /// Compresses files in the background once rotated.
+    /// Retention is applied once compression is done.
+    #[cfg(any(feature = "gzip", feature = "zstd"))]
+    pub fn with_compression(mut self, compression: Compression) -> Self {
+        let _ = self.compression.insert(compression);
+        self
+    }
+
+    /// How long lines may wait in the batch before being written. 1 second by default.
+    pub fn with_flush_interval(mut self, interval: Duration) -> Self {
+        self.flush_interval = interval;
+        self
+    }
+
+    /// Bytes of lines to batch before writing them without waiting for the flush interval.
+    /// 64 KiB by default.
+    pub fn with_batch_size(mut self, bytes: usize) -> Self {
+        self.batch_size = bytes;
+        self
+    }
+
+    /// Source of the time Daily and Hourly rotation go by, the system clock by default
+    pub fn with_clock(mut self, clock: impl Clock) -> Self {
+        self.clock = Box::new(clock);
+        self
+    }
+
+    /// Spawns the writer task, so it must be called within a tokio runtime
+    pub async fn build(self) -> Result<OnDisk, std::io::Error> {
+        let flush_interval = self.flush_interval;
+        let writer = self.open().await?;
+
+        let (tx, rx) = unbounded_channel();
+        tokio::spawn(writer.run(rx, flush_interval));
+
+        Ok(OnDisk { tx })
+    }
+
+    /// Runs the writer on a thread of its own instead, for use without a tokio runtime,
+    /// e.g. in sync binaries and plain #[test] functions.
+    ///
+    /// The thread is not joined when the process exits,
+    /// use OnDisk::flush_blocking or Log::shutdown_blocking before.
+    pub fn build_blocking(self) -> Result<OnDisk, std::io::Error> {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_time()
+            .build()?;
+
+        let flush_interval = self.flush_interval;
+        let (tx, rx) = unbounded_channel();
+        let (opened_tx, opened_rx) = std::sync::mpsc::sync_channel(1);
+
+        std::thread::Builder::new()
+            .name("rgb_log OnDisk".to_string())
+            .spawn(move || {
+                runtime.block_on(async move {
+                    match self.open().await {
+                        Ok(writer) => {
+                            let _ = opened_tx.send(Ok(()));
+                            writer.run(rx, flush_interval).await;
+                        }
+                        Err(err) => {
+                            let _ = opened_tx.send(Err(err));
+                        }
+                    }
+                })
+            })?;
+
+        opened_rx
+            .recv()
+            .map_err(|_| std::io::Error::other("OnDisk writer thread exited"))??;
+
+        Ok(OnDisk { tx })
+    }
+
+    async fn open(self) -> Result<Writer, std::io::Error> {
+        let file = open(&self.path).await?;
+        let metadata = file.metadata().await?;
+
+        // The file may be left over from an earlier period
+        let modified = metadata.modified().unwrap_or(self.clock.now());
+        let period = self.rotation.period(modified.into());
+
+        Ok(Writer {
+            path: self.path,
+            file: BufWriter::with_capacity(self.batch_size, file),
+            batch_size: self.batch_size,
+            size: metadata.len(),
+            period,
+            rotation: self.rotation,
+            retention: self.retention,
+            compression: self.compression,
+            compressing: JoinSet::new(),
+            in_flight: vec![],
+            clock: self.clock,
+        })
+    }
+}
+
+struct Writer {
+    path: PathBuf,
+    file: BufWriter<File>,
+    batch_size: usize,
+    size: u64,
+    /// Period the current file belongs to, for time based rotation
+    period: Option<String>,
+    rotation: Rotation,
+    retention: Retention,
+    compression: Option<Compression>,
+    /// Each task returns the path of the rotated file it compressed
+    compressing: JoinSet<PathBuf>,
+    /// Names of the rotated files being compressed and of their compressed files,
+    /// left alone by retention until compression is done
+    in_flight: Vec<OsString>,
+    clock: Box<dyn Clock>,
+}
+
+#[derive(Clone, Copy, Default)]
+struct Retention {
+    files: Option<usize>,
+    bytes: Option<u64>,
+}
+
+impl Writer {
+    /// Runs until every OnDisk handle is dropped
+    async fn run(mut self, mut rx: UnboundedReceiver<Msg>, flush_interval: Duration) {
+        let mut interval = tokio::time::interval(flush_interval);
+        interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+        let mut dirty = false;
+
+        loop {
+            tokio::select! {
+                msg = rx.recv() => match msg {
+                    Some(Msg::Line(line)) => {
+                        self.write(line.as_bytes()).await;
+                        if !dirty {
+                            // Counts from the first line waiting to be flushed
+                            interval.reset();
+                            dirty = true;
+                        }
+                    }
+                    Some(Msg::Flush(done)) => {
+                        self.flush().await;
+                        dirty = false;
+                        self.sync().await;
+                        let _ = done.send(());
+                    }
+                    None => {
+                        self.flush().await;
+                        self.sync().await;
+                        break;
+                    }
+                },
+                _ = interval.tick(), if dirty => {
+                    self.flush().await;
+                    dirty = false;
+                }
+            }
+        }
+    }
+
+    /// Written to the file by the BufWriter once batch_size is reached
+    async fn write(&mut self, buffer: &[u8]) {
+        if let Err(err) = self.rotate_if_due(buffer.len() as u64).await {
+            eprintln!("rgb_log: ERROR ROTATING OnDisk LOG: {err}");
+        }
+
+        let _ = self
+            .file
+            .write_all(buffer)
+            .await
+            .inspect_err(|err| eprintln!("rgb_log: ERROR WRITING OnDisk LOG: {err}"));
+
+        self.size += buffer.len() as u64;
+    }
+
+    async fn flush(&mut self) {
+        let _ = self
+            .file
+            .flush()
+            .await
+            .inspect_err(|err| eprintln!("rgb_log: ERROR FLUSHING OnDisk LOG: {err}"));
+    }
+
+    /// Waits for the file to reach the disk and for compression to finish
+    async fn sync(&mut self) {
+        let _ = self
+            .file
+            .get_ref()
+            .sync_data()
+            .await
+            .inspect_err(|err| eprintln!("rgb_log: ERROR SYNCING OnDisk LOG: {err}"));
+
+        while let Some(result) = self.compressing.join_next().await {
+            self.compressed(result);
+        }
+
+        let _ = self.remove_old().await;
+    }
+
+    async fn rotate_if_due(&mut self, incoming: u64) -> Result<(), std::io::Error> {
+        let suffix = match self.rotation {
+            Rotation::Never => return Ok(()),
+            Rotation::Size(max) => {
+                if self.size == 0 || self.size + incoming <= max {
+                    return Ok(());
+                }
+                let newest = rotated_files(&self.path, &[])
+                    .await?
+                    .iter()
+                    .filter_map(|rotated| rotated.order.number)
+                    .max();
+                (newest.unwrap_or(0) + 1).to_string()
+            }
+            Rotation::Daily | Rotation::Hourly => {
+                let period = self.rotation.period(self.clock.now().into());
+                if period == self.period {
+                    return Ok(());
+                }
+                let previous = std::mem::replace(&mut self.period, period);
+                if self.size == 0 {
+                    return Ok(());
+                }
+                previous.unwrap_or_default()
+            }
+        };
+
+        let rotated = self.rotate(suffix).await?;
+
+        if let Some(compression) = self.compression {
+            let compressed = with_suffix(&rotated, compression.extension());
+            self.in_flight.extend(
+                [&rotated, &compressed]
+                    .into_iter()
+                    .filter_map(|p| p.file_name().map(OsString::from)),
+            );
+
+            self.compressing.spawn(async move {
+                let compressing = rotated.clone();
+                let compressed =
+                    tokio::task::spawn_blocking(move || compression.compress(&compressing)).await;
+                match compressed {
+                    Ok(Ok(_)) => (),
+                    Ok(Err(err)) => {
+                        eprintln!("rgb_log: FAILED TO COMPRESS ROTATED LOG, ERROR: {err}")
+                    }
+                    Err(err) => eprintln!("rgb_log: COMPRESSION TASK FAILED: {err}"),
+                }
+                rotated
+            });
+        }
+
+        // Reap the compressions which are done
+        while let Some(result) = self.compressing.try_join_next() {
+            self.compressed(result);
+        }
+
+        self.remove_old().await
+    }
+
+    fn compressed(&mut self, result: Result<PathBuf, tokio::task::JoinError>) {
+        match result {
+            Ok(rotated) => {
+                let name = rotated.file_name().unwrap_or_default();
+                self.in_flight.retain(|in_flight| {
+                    let rest = in_flight
+                        .as_encoded_bytes()
+                        .strip_prefix(name.as_encoded_bytes());
+                    // The rotated file itself or rotated.extension
+                    !rest.is_some_and(|rest| rest.is_empty() || rest.starts_with(b"."))
+                });
+            }
+            Err(err) => eprintln!("rgb_log: COMPRESSION TASK FAILED: {err}"),
+        }
+
+        // Names of failed tasks are unknown, but nothing can be in flight without tasks
+        if self.compressing.is_empty() {
+            self.in_flight.clear();
+        }
+    }
+
+    async fn remove_old(&self) -> Result<(), std::io::Error> {
+        remove_old(&self.path, self.retention, &self.in_flight)
+            .await
+            .inspect_err(|err| eprintln!("rgb_log: ERROR REMOVING ROTATED LOGS: {err}"))
+    }
+
+    /// Renames the file to path.suffix and continues in a new one
+    async fn rotate(&mut self, suffix: String) -> Result<PathBuf, std::io::Error> {
+        self.file.flush().await?;
+
+        let mut rotated = with_suffix(&self.path, &suffix);
+
+        // Do not overwrite a rotated file from an earlier run
+        let mut n = 1;
+        while tokio::fs::try_exists(&rotated).await? {
+            rotated = with_suffix(&self.path, &format!("{suffix}.{n}"));
+            n += 1;
+        }
+
+        tokio::fs::rename(&self.path, &rotated).await?;
+        self.file = BufWriter::with_capacity(self.batch_size, open(&self.path).await?);
+        self.size = 0;
+
+        Ok(rotated)
+    }
+}
+
+/// Removes the oldest rotated files until those left are within retention.
+/// Files named in skip are neither removed nor counted.
+async fn remove_old(
+    path: &Path,
+    retention: Retention,
+    skip: &[OsString],
+) -> Result<(), std::io::Error> {
+    if retention.files.is_none() && retention.bytes.is_none() {
+        return Ok(());
+    }
+
+    let rotated = rotated_files(path, skip).await?;
+
+    let mut excess = retention
+        .files
+        .map_or(0, |files| rotated.len().saturating_sub(files));
+
+    if let Some(budget) = retention.bytes {
+        let mut total = 0;
+        let within_budget = rotated
+            .iter()
+            .rev()
+            .take_while(|rotated| {
+                total += rotated.len;
+                total <= budget
+            })
+            .count();
+        excess = excess.max(rotated.len() - within_budget);
+    }
+
+    for rotated in rotated.into_iter().take(excess) {
+        let p = path.with_file_name(rotated.name);
+        match tokio::fs::remove_file(&p).await {
+            Err(err) if err.kind() != ErrorKind::NotFound => eprintln!(
+                "rgb_log: FAILED TO REMOVE ROTATED LOG {}, ERROR: {err}",
+                p.to_string_lossy()
+            ),
+            _ => (),
+        }
+    }
+
+    Ok(())
+}
+
+struct RotatedFile {
+    order: RotatedName,
+    len: u64,
+    name: OsString,
+}
+
+/// Parts of a name given by rotate, ordered oldest first
+#[derive(PartialEq, Eq, PartialOrd, Ord, Debug)]
+struct RotatedName {
+    period: Option<String>,
+    number: Option<u64>,
+    /// n of the .n added against collisions, 0 without
+    collision: u64,
+}
+
+/// Rotated files next to the one at path, oldest first, leaving out those named in skip
+async fn rotated_files(path: &Path, skip: &[OsString]) -> Result<Vec<RotatedFile>, std::io::Error> {
+    let dir = match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    };
+
+    let mut rotated = vec![];
+    let mut entries = tokio::fs::read_dir(dir).await?;
+    while let Some(entry) = entries.next_entry().await? {
+        let name = entry.file_name();
+        if skip.contains(&name) {
+            continue;
+        }
+        let Some(order) = parse_rotated(path, &name) else {
+            continue;
+        };
+        let metadata = match entry.metadata().await {
+            Ok(metadata) => metadata,
+            // Removed since listing it, e.g. by compression
+            Err(err) if err.kind() == ErrorKind::NotFound => continue,
+            Err(err) => return Err(err),
+        };
+        rotated.push(RotatedFile {
+            order,
+            len: metadata.len(),
+            name,
+        });
+    }
+
+    rotated.sort_by(|a, b| a.order.cmp(&b.order).then_with(|| a.name.cmp(&b.name)));
+
+    Ok(rotated)
+}
+
+async fn open(p: &Path) -> Result<File, std::io::Error> {
+    File::options().append(true).create(true).open(p).await
+}
+
+fn with_suffix(path: &Path, suffix: &str) -> PathBuf {
+    let mut p = path.as_os_str().to_os_string();
+    p.push(".");
+    p.push(suffix);
+    PathBuf::from(p)
+}
+
+/// Parses names given by rotate: path.n or path.period,
+/// optionally followed by .n against collisions and the extension of a compression
+fn parse_rotated(path: &Path, name: &OsString) -> Option<RotatedName> {
+    let rest = name
+        .to_str()?
+        .strip_prefix(path.file_name()?.to_str()?)?
+        .strip_prefix('.')?;
+
+    let mut parts = rest.split('.').collect::<Vec<_>>();
+    if let Some(&"gz" | &"zst") = parts.last() {
+        parts.pop();
+    }
+
+    let number = |s: &str| {
+        s.bytes()
+            .all(|b| b.is_ascii_digit())
+            .then(|| s.parse::<u64>().ok())
+            .flatten()
+    };
+
+    let (suffix, collision) = match parts[..] {
+        [suffix] => (suffix, 0),
+        [suffix, n] => (suffix, number(n)?),
+        _ => return None,
+    };
+
+    if let Some(n) = number(suffix) {
+        Some(RotatedName {
+            period: None,
+            number: Some(n),
+            collision,
+        })
+    } else if is_period(suffix) {
+        Some(RotatedName {
+            period: Some(suffix.to_string()),
+            number: None,
+            collision,
+        })
+    } else {
+        None
+    }
+}
+
+/// `%Y-%m-%d` or `%Y-%m-%d-%H`, as made by Rotation::period
+fn is_period(s: &str) -> bool {
+    let (date, hour) = match s.get(10..) {
+        Some("") => (s, None),
+        Some(hour) => (&s[..10], hour.strip_prefix('-')),
+        None => return false,
+    };
+
+    let hour_ok =
+        hour.is_none_or(|hour| hour.len() == 2 && hour.parse::<u8>().is_ok_and(|hour| hour < 24));
+
+    hour_ok && chrono::NaiveDate::parse_from_str(date, "%Y-%m-%d").is_ok()
+}
+
+#[tokio::test]
+async fn rotates_by_size() {
+    let dir = std::env::temp_dir().join(format!("rgb_log_rotation_{}", std::process::id()));
+    let _ = tokio::fs::remove_dir_all(&dir).await;
+    tokio::fs::create_dir_all(&dir).await.unwrap();
+
+    let on_disk = OnDisk::builder(dir.join("app.log"))
+        .with_rotation(Rotation::Size(10))
+        .with_retention(2)
+        .build()
+        .await
+        .unwrap();
+
+    for n in 0..5 {
+        on_disk.push_line(format!("line {n}"));
+    }
+    on_disk.flush().await;
+
+    let mut names = std::fs::read_dir(&dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+        .collect::<Vec<_>>();
+    names.sort();
+
+    assert_eq!(names, ["app.log", "app.log.3", "app.log.4"]);
+    assert_eq!(
+        std::fs::read_to_string(dir.join("app.log.4")).unwrap(),
+        "line 3"
+    );
+
+    let _ = tokio::fs::remove_dir_all(&dir).await;
+}
+
+#[tokio::test]
+async fn rotates_by_period() {
+    let dir = std::env::temp_dir().join(format!("rgb_log_period_{}", std::process::id()));
+    let _ = tokio::fs::remove_dir_all(&dir).await;
+    tokio::fs::create_dir_all(&dir).await.unwrap();
+
+    let time = "2024-01-01T23:30:00Z".parse::<DateTime<Utc>>().unwrap();
+    assert_eq!(Rotation::Daily.period(time).as_deref(), Some("2024-01-01"));
+    assert_eq!(
+        Rotation::Hourly.period(time).as_deref(),
+        Some("2024-01-01-23")
+    );
+    assert_eq!(Rotation::Size(10).period(time), None);
+
+    // Left over from an earlier run
+    std::fs::write(dir.join("app.log.2024-01-01"), "earlier").unwrap();
+
+    let clock = crate::log::clock::ManualClock::new(time.into());
+    let on_disk = OnDisk::builder(dir.join("app.log"))
+        .with_rotation(Rotation::Daily)
+        .with_clock(clock.clone())
+        .build()
+        .await
+        .unwrap();
+
+    on_disk.push_line("first day".to_string());
+    on_disk.flush().await;
+    clock.advance(Duration::from_secs(3600));
+    on_disk.push_line("second day".to_string());
+    on_disk.flush().await;
+
+    let read = |name: &str| std::fs::read_to_string(dir.join(name)).unwrap();
+    assert_eq!(read("app.log.2024-01-01"), "earlier");
+    assert_eq!(read("app.log.2024-01-01.1"), "first day");
+    assert_eq!(read("app.log"), "second day");
+
+    let _ = tokio::fs::remove_dir_all(&dir).await;
+}
+
+#[tokio::test]
+async fn retention_keeps_unrelated_files() {
+    let dir = std::env::temp_dir().join(format!("rgb_log_unrelated_{}", std::process::id()));
+    let _ = tokio::fs::remove_dir_all(&dir).await;
+    tokio::fs::create_dir_all(&dir).await.unwrap();
+
+    let unrelated = ["app.log.bak", "app.log.lock", "app.log.2024-01-01.old"];
+    for name in unrelated {
+        std::fs::write(dir.join(name), "keep me").unwrap();
+    }
+
+    let on_disk = OnDisk::builder(dir.join("app.log"))
+        .with_rotation(Rotation::Size(5))
+        .with_retention(1)
+        .build()
+        .await
+        .unwrap();
+
+    for n in 0..4 {
+        on_disk.push_line(format!("line {n}"));
+    }
+    on_disk.flush().await;
+
+    for name in unrelated {
+        assert!(dir.join(name).exists(), "{name} was removed");
+    }
+    assert!(dir.join("app.log.3").exists());
+    assert!(!dir.join("app.log.2").exists());
+
+    assert_eq!(
+        parse_rotated(Path::new("app.log"), &"app.log.2024-01-01-23.1.zst".into()),
+        Some(RotatedName {
+            period: Some("2024-01-01-23".to_string()),
+            number: None,
+            collision: 1,
+        })
+    );
+    assert_eq!(
+        parse_rotated(Path::new("app.log"), &"app.log.1.txt".into()),
+        None
+    );
+
+    let _ = tokio::fs::remove_dir_all(&dir).await;
+}
+
+#[tokio::test]
+async fn size_budget_keeps_newest() {
+    let dir = std::env::temp_dir().join(format!("rgb_log_budget_{}", std::process::id()));
+    let _ = tokio::fs::remove_dir_all(&dir).await;
+    tokio::fs::create_dir_all(&dir).await.unwrap();
+
+    let on_disk = OnDisk::builder(dir.join("app.log"))
+        .with_rotation(Rotation::Size(10))
+        .with_size_budget(15)
+        .build()
+        .await
+        .unwrap();
+
+    // Each line of 6 bytes ends up in a rotated file of its own
+    for n in 0..5 {
+        on_disk.push_line(format!("line {n}"));
+    }
+    on_disk.flush().await;
+
+    let mut names = std::fs::read_dir(&dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+        .collect::<Vec<_>>();
+    names.sort();
+
+    assert_eq!(names, ["app.log", "app.log.3", "app.log.4"]);
+
+    let _ = tokio::fs::remove_dir_all(&dir).await;
+}
+
+#[cfg(feature = "gzip")]
+#[tokio::test]
+async fn compresses_rotated() {
+    let dir = std::env::temp_dir().join(format!("rgb_log_compression_{}", std::process::id()));
+    let _ = tokio::fs::remove_dir_all(&dir).await;
+    tokio::fs::create_dir_all(&dir).await.unwrap();
+
+    let on_disk = OnDisk::builder(dir.join("app.log"))
+        .with_rotation(Rotation::Size(10))
+        .with_compression(Compression::Gzip)
+        .build()
+        .await
+        .unwrap();
+
+    on_disk.push_line("line 0".to_string());
+    on_disk.push_line("line 1".to_string());
+    on_disk.flush().await;
+
+    assert!(!tokio::fs::try_exists(dir.join("app.log.1")).await.unwrap());
+
+    let mut decoded = String::new();
+    let f = std::fs::File::open(dir.join("app.log.1.gz")).unwrap();
+    std::io::Read::read_to_string(&mut flate2::read::GzDecoder::new(f), &mut decoded).unwrap();
+    assert_eq!(decoded, "line 0");
+
+    let _ = tokio::fs::remove_dir_all(&dir).await;
+}
+
+#[cfg(feature = "zstd")]
+#[tokio::test]
+async fn compresses_rotated_with_zstd() {
+    let dir = std::env::temp_dir().join(format!("rgb_log_zstd_{}", std::process::id()));
+    let _ = tokio::fs::remove_dir_all(&dir).await;
+    tokio::fs::create_dir_all(&dir).await.unwrap();
+
+    let on_disk = OnDisk::builder(dir.join("app.log"))
+        .with_rotation(Rotation::Size(10))
+        .with_retention(1)
+        .with_compression(Compression::Zstd)
+        .build()
+        .await
+        .unwrap();
+
+    for n in 0..4 {
+        on_disk.push_line(format!("line {n}"));
+    }
+    on_disk.flush().await;
+
+    let mut names = std::fs::read_dir(&dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+        .collect::<Vec<_>>();
+    names.sort();
+
+    assert_eq!(names, ["app.log", "app.log.3.zst"]);
+
+    let f = std::fs::File::open(dir.join("app.log.3.zst")).unwrap();
+    assert_eq!(zstd::stream::decode_all(f).unwrap(), b"line 2");
+
+    let _ = tokio::fs::remove_dir_all(&dir).await;
+}
+
+#[tokio::test]
+async fn batches_by_size_and_interval() {
+    let dir = std::env::temp_dir().join(format!("rgb_log_batching_{}", std::process::id()));
+    let _ = tokio::fs::remove_dir_all(&dir).await;
+    tokio::fs::create_dir_all(&dir).await.unwrap();
+    let read = |name: &str| std::fs::read_to_string(dir.join(name)).unwrap();
+    let written = async |name: &str| {
+        let deadline = std::time::Instant::now() + Duration::from_secs(5);
+        while read(name).is_empty() {
+            assert!(
+                std::time::Instant::now() < deadline,
+                "{name} was not written"
+            );
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        read(name)
+    };
+
+    let by_size = OnDisk::builder(dir.join("size.log"))
+        .with_flush_interval(Duration::from_secs(3600))
+        .with_batch_size(16)
+        .build()
+        .await
+        .unwrap();
+
+    // The first line is held until the second doesn't fit the batch, which is then held
+    by_size.push_line("0123456789\n".to_string());
+    by_size.push_line("abcdefghij\n".to_string());
+    assert_eq!(written("size.log").await, "0123456789\n");
+
+    let by_interval = OnDisk::builder(dir.join("interval.log"))
+        .with_flush_interval(Duration::from_millis(20))
+        .build()
+        .await
+        .unwrap();
+
+    by_interval.push_line("line\n".to_string());
+    assert_eq!(written("interval.log").await, "line\n");
+
+    let _ = tokio::fs::remove_dir_all(&dir).await;
+}
+
+#[tokio::test]
+async fn shutdown_writes_lines_from_threads_in_order() {
+    let path = std::env::temp_dir().join(format!("rgb_log_threads_{}.log", std::process::id()));
+    let _ = tokio::fs::remove_file(&path).await;
+
+    let on_disk = OnDisk::builder(&path)
+        .with_flush_interval(Duration::from_secs(3600))
+        .build()
+        .await
+        .unwrap();
+    let log = crate::Log::builder()
+        .with_buffer(on_disk)
+        .with_program_name(None)
+        .with_timestamp(crate::log::timestamp::Timestamp::None)
+        .with_format(crate::log::format::Format::Logfmt)
+        .without_console()
+        .build();
+
+    // Numbers are taken and logged under one lock, so they are submitted in order
+    let next = std::sync::Mutex::new(0);
+    std::thread::scope(|s| {
+        for _ in 0..4 {
+            s.spawn(|| {
+                for _ in 0..250 {
+                    let mut n = next.lock().unwrap();
+                    log.info(*n);
+                    *n += 1;
+                }
+            });
+        }
+    });
+
+    log.shutdown().await;
+
+    let expected = (0..1000)
+        .map(|n| format!("level=info msg={n}\r\n"))
+        .collect::<String>();
+    assert_eq!(tokio::fs::read_to_string(&path).await.unwrap(), expected);
+
+    let _ = tokio::fs::remove_file(path).await;
+}
+
+#[test]
+fn writes_without_runtime() {
+    let path = std::env::temp_dir().join(format!("rgb_log_blocking_{}.log", std::process::id()));
+    let _ = std::fs::remove_file(&path);
+
+    let on_disk = OnDisk::builder(&path).build_blocking().unwrap();
+
+    std::thread::scope(|s| {
+        s.spawn(|| on_disk.push_line("from a plain thread\n".to_string()));
+    });
+    on_disk.flush_blocking();
+
+    assert_eq!(
+        std::fs::read_to_string(&path).unwrap(),
+        "from a plain thread\n"
+    );
+
+    let _ = std::fs::remove_file(path);
+}
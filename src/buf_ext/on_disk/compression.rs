@@ -0,0 +1,68 @@
+use std::{
+    fs::File,
+    io::Write,
+    path::{Path, PathBuf},
+};
+
+use super::with_suffix;
+
+/// Format rotated files are compressed to, each behind its cargo feature
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Compression {
+    /// `app.log.1.gz`
+    #[cfg(feature = "gzip")]
+    Gzip,
+    /// `app.log.1.zst`
+    #[cfg(feature = "zstd")]
+    Zstd,
+}
+
+impl Compression {
+    pub(super) fn extension(&self) -> &'static str {
+        match *self {
+            #[cfg(feature = "gzip")]
+            Self::Gzip => "gz",
+            #[cfg(feature = "zstd")]
+            Self::Zstd => "zst",
+        }
+    }
+
+    /// Writes p with the extension of the format appended, then removes p. Blocks.
+    pub(super) fn compress(&self, p: &Path) -> Result<PathBuf, std::io::Error> {
+        let compressed = with_suffix(p, self.extension());
+
+        if let Err(err) = self.compress_into(p, &compressed) {
+            let _ = std::fs::remove_file(&compressed);
+            return Err(err);
+        }
+
+        std::fs::remove_file(p)?;
+        Ok(compressed)
+    }
+
+    // Without a compression feature there are no variants to match
+    #[allow(unreachable_code, unused_variables, unused_mut)]
+    fn compress_into(&self, p: &Path, compressed: &Path) -> Result<(), std::io::Error> {
+        let mut input = File::open(p)?;
+        let output = File::create(compressed)?;
+
+        let mut output: File = match *self {
+            #[cfg(feature = "gzip")]
+            Self::Gzip => {
+                let mut encoder =
+                    flate2::write::GzEncoder::new(output, flate2::Compression::default());
+                std::io::copy(&mut input, &mut encoder)?;
+                encoder.finish()?
+            }
+            #[cfg(feature = "zstd")]
+            Self::Zstd => {
+                let mut encoder = zstd::Encoder::new(output, zstd::DEFAULT_COMPRESSION_LEVEL)?;
+                std::io::copy(&mut input, &mut encoder)?;
+                encoder.finish()?
+            }
+        };
+
+        output.flush()?;
+        output.sync_all()
+    }
+}
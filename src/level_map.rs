@@ -0,0 +1,60 @@
+/// The levels of the log and tracing crates
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum FacadeLevel {
+    Error,
+    Warn,
+    Info,
+    Debug,
+    Trace,
+}
+
+/// Names of the configured levels that facade levels are printed as.
+/// Maps onto DEFAULT_LEVELS by default, WARN becomes ERROR and TRACE becomes DEBUG.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct LevelMap {
+    names: [&'static str; 5],
+}
+
+impl Default for LevelMap {
+    fn default() -> Self {
+        Self {
+            names: ["ERROR", "ERROR", "INFO", "DEBUG", "DEBUG"],
+        }
+    }
+}
+
+impl LevelMap {
+    pub fn set(&mut self, level: impl Into<FacadeLevel>, name: &'static str) {
+        self.names[level.into() as usize] = name;
+    }
+
+    pub fn get(&self, level: impl Into<FacadeLevel>) -> &'static str {
+        self.names[level.into() as usize]
+    }
+}
+
+#[cfg(feature = "log-ext")]
+impl From<::log::Level> for FacadeLevel {
+    fn from(level: ::log::Level) -> Self {
+        match level {
+            ::log::Level::Error => Self::Error,
+            ::log::Level::Warn => Self::Warn,
+            ::log::Level::Info => Self::Info,
+            ::log::Level::Debug => Self::Debug,
+            ::log::Level::Trace => Self::Trace,
+        }
+    }
+}
+
+#[cfg(feature = "tracing-ext")]
+impl From<&tracing_core::Level> for FacadeLevel {
+    fn from(level: &tracing_core::Level) -> Self {
+        match *level {
+            tracing_core::Level::ERROR => Self::Error,
+            tracing_core::Level::WARN => Self::Warn,
+            tracing_core::Level::INFO => Self::Info,
+            tracing_core::Level::DEBUG => Self::Debug,
+            tracing_core::Level::TRACE => Self::Trace,
+        }
+    }
+}
@@ -1,11 +1,33 @@
 #[cfg(feature = "buf-ext")]
 pub mod buf_ext;
 
+#[cfg(any(feature = "log-ext", feature = "tracing-ext"))]
+pub mod level_map;
+
+#[cfg(feature = "log-ext")]
+pub mod log_ext;
+
+#[cfg(feature = "tracing-ext")]
+pub mod tracing_ext;
+
 pub mod log;
 pub use log::Log;
 
 #[macro_export]
 macro_rules! info {
+    ($log:expr, $($key:ident = $value:expr),+ ; $($arg:tt)*) => {
+        // Keeps a temporary receiver alive for as long as the printer borrows it,
+        // and leaves the values unevaluated when the level is filtered out
+        match &$log {
+            log => {
+                if log.enabled("INFO") {
+                    let mut print = log.printer();
+                    $(print.field(stringify!($key), $value);)+
+                    print.info(format_args!($($arg)*));
+                }
+            }
+        }
+    };
     ($log:expr, $($arg:tt)*) => {
         $log.info(format_args!($($arg)*));
     };
@@ -16,6 +38,19 @@ macro_rules! info {
 
 #[macro_export]
 macro_rules! ok {
+    ($log:expr, $($key:ident = $value:expr),+ ; $($arg:tt)*) => {
+        // Keeps a temporary receiver alive for as long as the printer borrows it,
+        // and leaves the values unevaluated when the level is filtered out
+        match &$log {
+            log => {
+                if log.enabled("OK") {
+                    let mut print = log.printer();
+                    $(print.field(stringify!($key), $value);)+
+                    print.ok(format_args!($($arg)*));
+                }
+            }
+        }
+    };
     ($log:expr, $($arg:tt)*) => {
         $log.ok(format_args!($($arg)*));
     };
@@ -26,6 +61,19 @@ macro_rules! ok {
 
 #[macro_export]
 macro_rules! error {
+    ($log:expr, $($key:ident = $value:expr),+ ; $($arg:tt)*) => {
+        // Keeps a temporary receiver alive for as long as the printer borrows it,
+        // and leaves the values unevaluated when the level is filtered out
+        match &$log {
+            log => {
+                if log.enabled("ERROR") {
+                    let mut print = log.printer();
+                    $(print.field(stringify!($key), $value);)+
+                    print.error(format_args!($($arg)*));
+                }
+            }
+        }
+    };
     ($log:expr, $($arg:tt)*) => {
         $log.error(format_args!($($arg)*));
     };
@@ -36,6 +84,19 @@ macro_rules! error {
 
 #[macro_export]
 macro_rules! debug {
+    ($log:expr, $($key:ident = $value:expr),+ ; $($arg:tt)*) => {
+        // Keeps a temporary receiver alive for as long as the printer borrows it,
+        // and leaves the values unevaluated when the level is filtered out
+        match &$log {
+            log => {
+                if log.enabled("DEBUG") {
+                    let mut print = log.printer();
+                    $(print.field(stringify!($key), $value);)+
+                    print.debug(format_args!($($arg)*));
+                }
+            }
+        }
+    };
     ($log:expr, $($arg:tt)*) => {
         $log.debug(format_args!($($arg)*));
     };
@@ -43,3 +104,38 @@ macro_rules! debug {
         $submodulelog.debug(format_args!($($arg)*));
     };
 }
+
+#[test]
+fn macros_take_fields() {
+    let capture = log::Capture::default();
+    let log = Log::builder()
+        .with_buffer(capture.clone())
+        .without_console()
+        .build();
+
+    info!(log, user_id = 42, name = "ferris"; "logged in");
+    error!(log.submodule("auth"), attempts = 3; "locked out");
+
+    let lines = capture.0.lock().unwrap();
+    assert!(lines[0].ends_with("INFO: logged in user_id=42 name=ferris\r\n"));
+    assert!(lines[1].contains("auth"));
+    assert!(lines[1].ends_with("ERROR: locked out attempts=3\r\n"));
+}
+
+#[test]
+fn filtered_macros_skip_field_values() {
+    let log = Log::builder()
+        .with_min_level("INFO")
+        .without_console()
+        .build();
+
+    let mut evaluated = false;
+    let mut side = || {
+        evaluated = true;
+        1
+    };
+    debug!(log, x = side(); "hidden");
+    debug!(log.submodule("main"), x = side(); "hidden");
+
+    assert!(!evaluated);
+}
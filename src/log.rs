@@ -1,17 +1,37 @@
-use std::{collections::HashMap, sync::Arc};
-
-use crossterm::style::StyledContent;
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
 
 mod buf;
 mod builder;
+pub mod clock;
 pub mod color;
+pub mod field;
+pub mod filter;
+pub mod format;
 pub mod padding;
 pub mod program_name;
-
-pub use buf::LogBuffer;
+mod record;
+pub mod sink;
+pub mod target;
+pub mod timestamp;
+
+#[cfg(test)]
+pub(crate) use buf::Capture;
+pub use buf::{FlushFuture, LogBuffer, Tee};
+use clock::Clock;
 use color::Color;
+use field::Value;
+use filter::LevelFilter;
+use format::Format;
 use padding::PadLeft;
 use program_name::ProgramName;
+pub use record::LogRecord;
+use sink::Output;
+use target::Target;
+use timestamp::{Elapsed, TimeZone, Timestamp};
 
 pub use builder::Builder as LogBuilder;
 
@@ -22,18 +42,29 @@ pub const DEFAULT_LEVELS: [Color; 4] = [
     Color::Cyan("DEBUG"),
 ];
 
+/// Severity order of DEFAULT_LEVELS, least severe first
+pub const DEFAULT_SEVERITY: [&str; 4] = ["DEBUG", "INFO", "OK", "ERROR"];
+
 pub struct Log {
-    buf: Box<dyn LogBuffer>,
+    console: Output<Option<Target>>,
+    sinks: Vec<Output<Box<dyn LogBuffer>>>,
     program_name: Option<String>,
     submodule_pad: PadLeft<'static>,
     level_pad: PadLeft<'static>,
-    level_color: HashMap<&'static str, StyledContent<&'static str>>,
+    /// Levels wrapped in their escape codes
+    level_color: HashMap<&'static str, String>,
+    filter: LevelFilter,
+    timestamp: Timestamp,
+    time_zone: TimeZone,
+    elapsed: Option<Elapsed>,
+    clock: Box<dyn Clock>,
+    start: Instant,
+    last: Mutex<Instant>,
 }
 
 impl<'b> Log {
     pub fn new() -> Arc<Self> {
         Self::builder()
-            .with_buffer(None)
             .with_program_name(ProgramName::CrateName)
             .with_submodule_names([])
             .with_levels(DEFAULT_LEVELS)
@@ -44,6 +75,11 @@ impl<'b> Log {
         SubmoduleLog::new(self.clone(), name)
     }
 
+    /// Starts a line which can be given fields before it is output
+    pub fn printer<L: Loggable>(&'b self) -> Print<'b, 'b, L> {
+        Print::new(self, (None, None, None))
+    }
+
     pub fn debug<L: Loggable>(&'b self, content: L) {
         Print::new(self, (None, Some("DEBUG"), Some(content))).print();
     }
@@ -60,6 +96,42 @@ impl<'b> Log {
         Print::new(self, (None, Some("ERROR"), Some(content))).print();
     }
 
+    /// Changes the minimum level that gets printed.
+    /// Submodules with a filter directive of their own keep their threshold.
+    /// Returns false if the level is not part of the severity order, leaving the threshold as is.
+    pub fn set_min_level(&self, level: &str) -> bool {
+        self.filter.set_min_level(level)
+    }
+
+    /// Levels missing from the severity order are always enabled
+    pub fn enabled(&self, level: &str) -> bool {
+        self.enabled_for(None, level)
+    }
+
+    /// Like enabled, but honors the filter directive of the submodule if there is one
+    pub fn enabled_for(&self, submod: Option<&str>, level: &str) -> bool {
+        self.filter.enabled(submod, level)
+    }
+
+    /// Waits for the buffers to write out the lines they hold back, see LogBuffer::flush.
+    /// Meant to be awaited before the process exits.
+    pub async fn shutdown(&self) {
+        self.flush().await;
+    }
+
+    /// Like shutdown, for code without an async runtime. Blocks the current thread.
+    pub fn shutdown_blocking(&self) {
+        buf::block_on(self.flush());
+    }
+
+    fn flush(&self) -> FlushFuture<'_> {
+        Box::pin(async {
+            for sink in self.sinks.iter() {
+                sink.target.flush().await;
+            }
+        })
+    }
+
     //pub async fn get_buf(&self) -> RwLockReadGuard<'_, Vec<String>> {
     //    self.buf.read().await
     //}
@@ -76,6 +148,15 @@ impl SubmoduleLog {
         Self { log, submod }
     }
 
+    pub fn enabled(&self, level: &str) -> bool {
+        self.log.enabled_for(Some(self.submod), level)
+    }
+
+    /// Starts a line which can be given fields before it is output
+    pub fn printer<L: Loggable>(&self) -> Print<'_, '_, L> {
+        Print::new(&self.log, (Some(self.submod), None, None))
+    }
+
     pub fn debug<L: Loggable>(&self, content: L) {
         Print::new(&self.log, (Some(self.submod), Some("DEBUG"), Some(content))).print();
     }
@@ -98,6 +179,7 @@ pub struct Print<'a, 'b, L: Loggable> {
     submod: Option<&'a str>,
     level: Option<&'a str>,
     content: Option<L>,
+    fields: Vec<(&'a str, Value)>,
 }
 
 impl<'a, 'b, L: Loggable> Print<'a, 'b, L> {
@@ -110,6 +192,7 @@ impl<'a, 'b, L: Loggable> Print<'a, 'b, L> {
             submod,
             level,
             content,
+            fields: vec![],
         }
     }
 
@@ -128,6 +211,12 @@ impl<'a, 'b, L: Loggable> Print<'a, 'b, L> {
         self
     }
 
+    /// Appends a key=value pair, rendered after the content
+    pub fn field(&mut self, key: &'a str, value: impl Into<Value>) -> &mut Self {
+        self.fields.push((key, value.into()));
+        self
+    }
+
     // Output methods
 
     pub fn debug(mut self, content: L) {
@@ -160,82 +249,166 @@ impl<'a, 'b, L: Loggable> Print<'a, 'b, L> {
     }
 
     pub fn print(self) {
-        let line = self.get_line();
-        print!("{line}");
+        if !self.enabled() {
+            return;
+        }
 
-        self.log.buf.push_line(line);
+        let log = self.log;
+        log.output(self.into_record(), false);
     }
 
-    pub fn into_line(self) -> String {
-        let line = self.get_line();
-        print!("{line}");
+    /// Returns None when the level is filtered out
+    pub fn into_line(self) -> Option<String> {
+        if !self.enabled() {
+            return None;
+        }
+
+        let log = self.log;
+        log.output(self.into_record(), true)
+    }
+
+    // priv
+
+    fn enabled(&self) -> bool {
+        self.log
+            .enabled_for(self.submod, self.level.unwrap_or("DEBUG"))
+    }
+
+    fn into_record(self) -> LogRecord {
+        LogRecord {
+            timestamp: self.log.clock.now(),
+            elapsed: self.log.elapsed(self.log.clock.instant()),
+            program: self.log.program_name.clone(),
+            submodule: self.submod.map(str::to_string),
+            level: self.level.unwrap_or("DEBUG").to_string(),
+            message: self
+                .content
+                .as_ref()
+                .map_or(Default::default(), |l| l.as_loggable()),
+            fields: self
+                .fields
+                .into_iter()
+                .map(|(key, value)| (key.to_string(), value))
+                .collect(),
+            line: String::new(),
+        }
+    }
+}
+
+impl Log {
+    fn elapsed(&self, now: Instant) -> Option<Duration> {
+        match self.elapsed? {
+            Elapsed::SinceStart => Some(now.saturating_duration_since(self.start)),
+            Elapsed::SinceLast => {
+                let mut last = self.last.lock().unwrap_or_else(|e| e.into_inner());
+                let since_last = now.saturating_duration_since(*last);
+                *last = now;
+                Some(since_last)
+            }
+        }
+    }
+
+    /// Hands the record to every output whose threshold it passes,
+    /// rendering it once per format and coloring in use.
+    /// Returns the line as rendered for the terminal, even without console output, if asked to.
+    fn output(&self, mut record: LogRecord, want_line: bool) -> Option<String> {
+        let timestamp = self.timestamp.render(record.timestamp, self.time_zone);
+        let rank = self.filter.rank(&record.level);
+
+        let mut rendered: Vec<(Format, bool, String)> = vec![];
+        let mut render = |record: &LogRecord, format: Format, colored: bool| {
+            let cached = rendered
+                .iter()
+                .find(|(f, c, _)| *f == format && *c == colored);
+            match cached {
+                Some((_, _, line)) => line.clone(),
+                None => {
+                    let line = self.render(record, format, timestamp.as_deref(), colored);
+                    rendered.push((format, colored, line.clone()));
+                    line
+                }
+            }
+        };
 
-        self.log.buf.push_line(line.clone());
+        let console = &self.console;
+        let line = (console.target.is_some() || want_line)
+            .then(|| render(&record, console.format, console.colored));
+
+        if let (Some(target), Some(line)) = (&console.target, &line)
+            && console.passes(rank)
+        {
+            let to_stderr = match target {
+                Target::Split(level) => rank
+                    .zip(self.filter.rank(level))
+                    .is_some_and(|(rank, min)| rank >= min),
+                _ => false,
+            };
+            target.write(line, to_stderr);
+        }
+
+        for sink in self.sinks.iter().filter(|sink| sink.passes(rank)) {
+            record.line = render(&record, sink.format, sink.colored);
+            sink.target.push_record(record.clone());
+        }
 
         line
     }
 
-    // priv
+    fn render(
+        &self,
+        record: &LogRecord,
+        format: Format,
+        timestamp: Option<&str>,
+        colored: bool,
+    ) -> String {
+        match format {
+            Format::Human => self.render_human(record, timestamp, colored),
+            Format::Json => format::json(record, timestamp),
+            Format::Logfmt => format::logfmt(record, timestamp),
+        }
+    }
 
-    #[allow(unreachable_code)]
-    fn get_line(&self) -> String {
-        let program_and_modpart = if let Some(ref program) = self.log.program_name {
-            match self.submod {
+    fn render_human(&self, record: &LogRecord, timestamp: Option<&str>, colored: bool) -> String {
+        let program_and_modpart = if let Some(ref program) = record.program {
+            match record.submodule.as_deref() {
                 Some(submod) => {
-                    format!("[{program} {}]", self.log.submodule_pad.get(submod))
+                    format!("[{program} {}]", self.submodule_pad.get(submod))
                 }
                 None => {
-                    let spacing = " ".repeat(self.log.submodule_pad.width.into());
+                    let spacing = " ".repeat(self.submodule_pad.width.into());
                     format!("[{program}] {spacing}")
                 }
             }
         } else {
-            match self.submod {
+            match record.submodule.as_deref() {
                 Some(submod) => {
-                    format!("[{}]", self.log.submodule_pad.get(submod))
-                }
-                None => {
-                    let spacing = " ".repeat(self.log.submodule_pad.width.into());
-                    format!("{spacing}")
+                    format!("[{}]", self.submodule_pad.get(submod))
                 }
+                None => " ".repeat(self.submodule_pad.width.into()),
             }
         };
 
-        let (padding, level) = self.log.level_pad.get_split(self.level.unwrap_or("DEBUG"));
-        let level = self
-            .log
-            .level_color
-            .get(level)
-            .map(|s| format!("{padding}{s}"))
-            .unwrap_or(format!("{padding}{level}"));
-
-        let content = self.get_content();
-
-        #[cfg(feature = "chrono")]
-        {
-            use chrono::{SecondsFormat, Utc};
-
-            #[cfg(feature = "seconds")]
-            let timestamp = Utc::now().to_rfc3339_opts(SecondsFormat::Secs, true);
-            #[cfg(feature = "milliseconds")]
-            let timestamp = Utc::now().to_rfc3339_opts(SecondsFormat::Millis, true);
-
-            #[cfg(feature = "rfc")]
-            {
-                return format!("{timestamp} {program_and_modpart} {level}: {content}\r\n");
-            }
+        let (padding, level) = self.level_pad.get_split(&record.level);
+        let level = match self.level_color.get(level) {
+            Some(s) if colored => format!("{padding}{s}"),
+            _ => format!("{padding}{level}"),
+        };
 
-            let date_time = timestamp.replace("T", " ").replace("Z", "");
-            return format!("{date_time} {program_and_modpart} {level}: {content}\r\n");
+        let mut content = record.message.clone();
+        for (key, value) in record.fields.iter() {
+            content.push_str(&format!(" {key}={value}"));
         }
 
-        format!("{program_and_modpart} {level}: {content}\r\n")
-    }
-
-    fn get_content(&self) -> String {
-        self.content
-            .as_ref()
-            .map_or(Default::default(), |l| l.as_loggable())
+        let mut line = String::new();
+        if let Some(timestamp) = timestamp {
+            line.push_str(&self.timestamp.human(timestamp));
+            line.push(' ');
+        }
+        if let Some(elapsed) = record.elapsed {
+            line.push_str(&format!("{:>9} ", timestamp::human_elapsed(elapsed)));
+        }
+        line.push_str(&format!("{program_and_modpart} {level}: {content}\r\n"));
+        line
     }
 }
 
@@ -248,3 +421,151 @@ impl<T: ToString> Loggable for T {
         self.to_string()
     }
 }
+
+#[test]
+fn min_level_filters_less_severe() {
+    let log = Log::builder().with_min_level("INFO").build();
+
+    assert!(!log.enabled("DEBUG"));
+    assert!(log.enabled("ERROR"));
+    assert!(log.enabled("NOT_RANKED"));
+
+    assert!(log.set_min_level("DEBUG"));
+    assert!(log.enabled("DEBUG"));
+    assert!(!log.set_min_level("NOT_RANKED"));
+}
+
+#[test]
+fn submodule_directives_override_min_level() {
+    let log = Log::builder()
+        .with_submodule_names(["very_long_subject", "main"])
+        .with_filter("info,very_long_subject=debug,ghost_mod=off")
+        .build();
+
+    assert!(log.submodule("very_long_subject").enabled("DEBUG"));
+    assert!(!log.submodule("main").enabled("DEBUG"));
+    assert!(!log.submodule("ghost_mod").enabled("ERROR"));
+    assert!(log.submodule("unfiltered_ghost").enabled("INFO"));
+}
+
+#[test]
+fn buffer_gets_plain_lines() {
+    let capture = Capture::default();
+    let log = Log::builder()
+        .with_buffer(capture.clone())
+        .with_color(color::ColorMode::Always)
+        .build();
+
+    log.error("plain");
+
+    let lines = capture.0.lock().unwrap();
+    assert!(lines[0].ends_with("ERROR: plain\r\n"));
+}
+
+#[test]
+fn sinks_have_own_level_and_format() {
+    let (errors, json) = (Capture::default(), Capture::default());
+    let log = Log::builder()
+        .with_sink(errors.clone(), sink::Sink::new().with_min_level("ERROR"))
+        .with_sink(json.clone(), sink::Sink::new().with_format(Format::Json))
+        .with_console_sink(sink::Sink::new().with_min_level("INFO"))
+        .build();
+
+    log.debug("hidden from the console");
+    log.error("everywhere");
+
+    assert_eq!(errors.0.lock().unwrap().len(), 1);
+
+    let json = json.0.lock().unwrap();
+    assert_eq!(json.len(), 2);
+    assert!(json[0].starts_with('{'));
+}
+
+#[test]
+fn writer_target_gets_console_lines() {
+    use std::{io::Write, sync::Mutex};
+
+    #[derive(Clone, Default)]
+    struct Shared(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for Shared {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    let out = Shared::default();
+    let log = Log::builder()
+        .with_target(Target::writer(out.clone()))
+        .with_color(color::ColorMode::Auto)
+        .with_program_name(None)
+        .build();
+
+    log.submodule("main").ok("captured");
+
+    let out = String::from_utf8(out.0.lock().unwrap().clone()).unwrap();
+    // Not a terminal, so no colors either
+    assert!(out.contains("[main]"));
+    assert!(out.ends_with(" OK: captured\r\n"));
+}
+
+#[test]
+fn without_console_still_buffers() {
+    let capture = Capture::default();
+    let log = Log::builder()
+        .without_console()
+        .with_buffer(capture.clone())
+        .build();
+
+    let mut print = log.printer();
+    print.level("INFO").content("only buffered");
+    let line = print.into_line();
+
+    assert!(line.is_some_and(|line| line.ends_with("INFO: only buffered\r\n")));
+    assert_eq!(capture.0.lock().unwrap().len(), 1);
+}
+
+#[test]
+fn elapsed_column() {
+    let capture = Capture::default();
+    let log = Log::builder()
+        .with_buffer(capture.clone())
+        .with_timestamp(Timestamp::None)
+        .with_elapsed(Elapsed::SinceStart)
+        .with_format(Format::Logfmt)
+        .build();
+
+    log.info("started");
+
+    let lines = capture.0.lock().unwrap();
+    assert!(lines[0].starts_with("elapsed=0."));
+}
+
+#[test]
+fn manual_clock_snapshot() {
+    use clock::ManualClock;
+    use std::time::UNIX_EPOCH;
+    use timestamp::Precision;
+
+    let clock = ManualClock::new(UNIX_EPOCH + Duration::from_secs(1_750_000_000));
+    let capture = Capture::default();
+    let log = Log::builder()
+        .with_buffer(capture.clone())
+        .with_program_name(None)
+        .with_clock(clock.clone())
+        .with_timestamp(Timestamp::Unix(Precision::Seconds))
+        .with_elapsed(Elapsed::SinceStart)
+        .build();
+
+    log.info("first");
+    clock.advance(Duration::from_millis(1500));
+    log.error("later");
+
+    let lines = capture.0.lock().unwrap();
+    assert_eq!(lines[0], "1750000000   +0.000s   INFO: first\r\n");
+    assert_eq!(lines[1], "1750000001   +1.500s  ERROR: later\r\n");
+}
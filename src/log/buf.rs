@@ -1,8 +1,232 @@
+use std::{
+    collections::HashMap,
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Wake, Waker},
+    thread::Thread,
+};
+
+use crate::log::{DEFAULT_SEVERITY, LogRecord};
+
+/// Returned by LogBuffer::flush
+pub type FlushFuture<'a> = Pin<Box<dyn Future<Output = ()> + Send + 'a>>;
+
 pub trait LogBuffer: 'static + Send + Sync {
     //async fn push_line<S: Sized + AsRef<str> + ToString + Send>(&self, line: S);
     fn push_line(&self, line: String);
+
+    /// Called by Log for every line.
+    /// Pushes the line rendered in the format of its sink by default.
+    fn push_record(&self, record: LogRecord) {
+        self.push_line(record.into_line());
+    }
+
+    /// Whether lines pushed to the buffer keep the color escape codes of the level column.
+    /// Plain lines by default.
+    fn colored(&self) -> bool {
+        false
+    }
+
+    /// Resolves once lines pushed so far are no longer held back by the buffer.
+    /// Buffers which store lines right away have nothing to wait for.
+    fn flush(&self) -> FlushFuture<'_> {
+        Box::pin(async {})
+    }
+
+    /// Called by LogBuilder::build with the rank of each level in the severity order of the Log,
+    /// for buffers filtering levels themselves
+    fn set_severity(&mut self, _severity: &HashMap<&'static str, u8>) {}
 }
 
 impl LogBuffer for Option<()> {
     fn push_line(&self, _line: String) {}
+
+    // Nothing is kept, so save rendering the line a second time without colors
+    fn colored(&self) -> bool {
+        true
+    }
+}
+
+/// Dispatches every line to several buffers, like lines in memory and on disk at once.
+/// Children get the line without colors, whatever they return from LogBuffer::colored.
+pub struct Tee {
+    children: Vec<Child>,
+    severity: HashMap<&'static str, u8>,
+}
+
+struct Child {
+    buf: Box<dyn LogBuffer>,
+    min_level: Option<&'static str>,
+    rank: Option<u8>,
+}
+
+impl Default for Tee {
+    /// Ranks levels by DEFAULT_SEVERITY until given to a Log
+    fn default() -> Self {
+        Self {
+            children: vec![],
+            severity: DEFAULT_SEVERITY
+                .into_iter()
+                .enumerate()
+                .map(|(rank, level)| (level, rank as u8))
+                .collect(),
+        }
+    }
+}
+
+impl Tee {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a buffer receiving every line
+    pub fn with(mut self, bfr: impl LogBuffer) -> Self {
+        self.children.push(Child {
+            buf: Box::new(bfr),
+            min_level: None,
+            rank: None,
+        });
+        self
+    }
+
+    /// Adds a buffer which only receives lines of this level and more severe ones.
+    /// Levels missing from the severity order are always received.
+    pub fn with_min_level(mut self, bfr: impl LogBuffer, level: &'static str) -> Self {
+        self.children.push(Child {
+            buf: Box::new(bfr),
+            min_level: Some(level),
+            rank: self.severity.get(level).copied(),
+        });
+        self
+    }
+}
+
+impl LogBuffer for Tee {
+    fn push_line(&self, line: String) {
+        for child in self.children.iter() {
+            child.buf.push_line(line.clone());
+        }
+    }
+
+    fn push_record(&self, record: LogRecord) {
+        let rank = self.severity.get(record.level.as_str());
+
+        for child in self.children.iter() {
+            let wants = match (child.rank, rank) {
+                (Some(min), Some(rank)) => *rank >= min,
+                _ => true,
+            };
+            if wants {
+                child.buf.push_record(record.clone());
+            }
+        }
+    }
+
+    fn flush(&self) -> FlushFuture<'_> {
+        Box::pin(async {
+            for child in self.children.iter() {
+                child.buf.flush().await;
+            }
+        })
+    }
+
+    fn set_severity(&mut self, severity: &HashMap<&'static str, u8>) {
+        self.severity = severity.clone();
+
+        for child in self.children.iter_mut() {
+            child.buf.set_severity(severity);
+            child.rank = child.min_level.and_then(|level| {
+                let rank = severity.get(level).copied();
+                if rank.is_none() {
+                    eprintln!("rgb_log: TEE MIN LEVEL '{level}' IS NOT PART OF THE SEVERITY ORDER");
+                }
+                rank
+            });
+        }
+    }
+}
+
+/// Keeps pushed lines for tests to look at
+#[cfg(test)]
+#[derive(Clone, Default)]
+pub(crate) struct Capture(pub Arc<std::sync::Mutex<Vec<String>>>);
+
+#[cfg(test)]
+impl LogBuffer for Capture {
+    fn push_line(&self, line: String) {
+        self.0.lock().unwrap().push(line);
+    }
+}
+
+/// Polls the future on the current thread, parking it while the future is pending
+pub(crate) fn block_on(mut future: FlushFuture<'_>) {
+    struct Unpark(Thread);
+
+    impl Wake for Unpark {
+        fn wake(self: Arc<Self>) {
+            self.0.unpark();
+        }
+    }
+
+    let waker = Waker::from(Arc::new(Unpark(std::thread::current())));
+    let mut cx = Context::from_waker(&waker);
+
+    while future.as_mut().poll(&mut cx).is_pending() {
+        std::thread::park();
+    }
+}
+
+#[test]
+fn tee_filters_per_child() {
+    let (all, severe) = (Capture::default(), Capture::default());
+    let tee = Tee::new()
+        .with(all.clone())
+        .with_min_level(severe.clone(), "WARN");
+
+    let log = crate::Log::builder()
+        .with_severity(["DEBUG", "INFO", "WARN", "ERROR"])
+        .with_buffer(tee)
+        .without_console()
+        .build();
+
+    log.info("started");
+    log.error("failed");
+    let mut audit = log.printer();
+    audit.level("AUDIT");
+    audit.printc("unranked");
+
+    assert_eq!(all.0.lock().unwrap().len(), 3);
+    assert_eq!(severe.0.lock().unwrap().len(), 2);
+}
+
+#[test]
+fn push_record_receives_fields() {
+    use crate::log::field::Value;
+    use std::sync::Mutex;
+
+    #[derive(Clone, Default)]
+    struct Fields(Arc<Mutex<Vec<(String, Value)>>>);
+
+    impl LogBuffer for Fields {
+        fn push_line(&self, _line: String) {
+            unreachable!("push_record is overridden");
+        }
+
+        fn push_record(&self, record: LogRecord) {
+            self.0.lock().unwrap().extend(record.fields);
+        }
+    }
+
+    let fields = Fields::default();
+    let log = crate::Log::builder()
+        .with_buffer(fields.clone())
+        .without_console()
+        .build();
+
+    crate::info!(log, user_id = 42, name = "ferris"; "logged in");
+
+    let fields = fields.0.lock().unwrap();
+    assert_eq!(fields[0].0, "user_id");
+    assert!(matches!(fields[0].1, Value::Int(42)));
+    assert!(matches!(fields[1].1, Value::Str(ref name) if name == "ferris"));
 }
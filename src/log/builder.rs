@@ -1,15 +1,38 @@
-use std::sync::Arc;
+use std::{collections::HashMap, sync::Arc};
 
 use crossterm::style::StyledContent;
 
-use crate::log::{DEFAULT_LEVELS, LogBuffer, PadLeft, color::GetColor, program_name::ProgramName};
+use crate::log::{
+    DEFAULT_LEVELS, DEFAULT_SEVERITY, LogBuffer, PadLeft,
+    clock::{Clock, SystemClock},
+    color::{self, ColorMode, GetColor},
+    filter::{DEFAULT_FILTER_ENV, Directives, LevelFilter},
+    format::Format,
+    program_name::ProgramName,
+    sink::{Output, Sink},
+    target::Target,
+    timestamp::{Elapsed, TimeZone, Timestamp},
+};
 
 #[derive(Default)]
 pub struct Builder {
     buffer: Option<Box<dyn LogBuffer>>,
+    sinks: Vec<(Box<dyn LogBuffer>, Sink)>,
+    console: Sink,
+    target: Target,
+    without_console: bool,
+    timestamp: Timestamp,
+    time_zone: TimeZone,
+    elapsed: Option<Elapsed>,
+    clock: Option<Box<dyn Clock>>,
     program_name: Option<ProgramName>,
     submodule_names: Option<PadLeft<'static>>,
     levels: Option<Vec<(&'static str, StyledContent<&'static str>)>>,
+    severity: Option<Vec<&'static str>>,
+    min_level: Option<&'static str>,
+    directives: Option<Directives>,
+    format: Format,
+    color: ColorMode,
 }
 
 impl Builder {
@@ -18,6 +41,30 @@ impl Builder {
         self
     }
 
+    /// Adds a buffer next to the one of with_buffer, with a threshold and format of its own
+    pub fn with_sink(mut self, bfr: impl LogBuffer, sink: Sink) -> Self {
+        self.sinks.push((Box::new(bfr), sink));
+        self
+    }
+
+    /// Threshold and format of the lines printed to the terminal
+    pub fn with_console_sink(mut self, sink: Sink) -> Self {
+        self.console = sink;
+        self
+    }
+
+    /// Where console lines are written to, stdout by default
+    pub fn with_target(mut self, target: Target) -> Self {
+        self.target = target;
+        self
+    }
+
+    /// Lines only go to the buffers, see with_buffer and with_sink
+    pub fn without_console(mut self) -> Self {
+        self.without_console = true;
+        self
+    }
+
     pub fn with_program_name(mut self, v: impl Into<ProgramName>) -> Self {
         let _ = self.program_name.insert(v.into());
         self
@@ -44,8 +91,74 @@ impl Builder {
         self
     }
 
+    /// Orders level names from least to most severe.
+    /// Levels left out of the order are never filtered.
+    pub fn with_severity<I>(mut self, iter: I) -> Self
+    where
+        I: IntoIterator<Item = &'static str>,
+    {
+        let _ = self.severity.insert(iter.into_iter().collect());
+        self
+    }
+
+    /// Levels less severe than this one are not printed.
+    /// Can be changed later with Log::set_min_level
+    pub fn with_min_level(mut self, level: &'static str) -> Self {
+        let _ = self.min_level.insert(level);
+        self
+    }
+
+    /// Sets per submodule minimum levels from directives like `info,very_long_subject=debug`.
+    /// A bare level replaces the one given to with_min_level.
+    pub fn with_filter(mut self, directives: &str) -> Self {
+        let _ = self.directives.insert(Directives::parse(directives));
+        self
+    }
+
+    /// Like with_filter, reading the directives from an environment variable.
+    /// Defaults to RGB_LOG when var is None. Nothing changes if the variable is unset.
+    pub fn with_env_filter(mut self, var: Option<&str>) -> Self {
+        if let Some(directives) = Directives::from_env(var.unwrap_or(DEFAULT_FILTER_ENV)) {
+            let _ = self.directives.insert(directives);
+        }
+        self
+    }
+
+    pub fn with_format(mut self, format: Format) -> Self {
+        self.format = format;
+        self
+    }
+
+    pub fn with_color(mut self, color: ColorMode) -> Self {
+        self.color = color;
+        self
+    }
+
+    /// Defaults to RFC 3339 in the precision of the seconds/milliseconds features
+    pub fn with_timestamp(mut self, timestamp: Timestamp) -> Self {
+        self.timestamp = timestamp;
+        self
+    }
+
+    /// UTC by default
+    pub fn with_time_zone(mut self, zone: TimeZone) -> Self {
+        self.time_zone = zone;
+        self
+    }
+
+    /// Adds a column of time since build or since the previous line
+    pub fn with_elapsed(mut self, elapsed: Elapsed) -> Self {
+        let _ = self.elapsed.insert(elapsed);
+        self
+    }
+
+    /// Where the timestamp and elapsed column come from, the system clock by default
+    pub fn with_clock(mut self, clock: impl Clock) -> Self {
+        let _ = self.clock.insert(Box::new(clock));
+        self
+    }
+
     pub fn build(self) -> Arc<super::Log> {
-        let buf = self.buffer.unwrap_or(Box::new(None));
         let program_name = self
             .program_name
             .unwrap_or(ProgramName::CrateName)
@@ -61,28 +174,114 @@ impl Builder {
                     .collect()
             };
 
-        super::Log::new_raw(buf, program_name, submodule_names, levels)
+        let severity: HashMap<&'static str, u8> = self
+            .severity
+            .unwrap_or(DEFAULT_SEVERITY.to_vec())
+            .into_iter()
+            .enumerate()
+            .map(|(rank, level)| (level, rank as u8))
+            .collect();
+        let rank = |level: &str| {
+            let rank = severity.get(level).copied();
+            if rank.is_none() {
+                eprintln!("rgb_log: MIN LEVEL '{level}' IS NOT PART OF THE SEVERITY ORDER");
+            }
+            rank
+        };
+
+        let min_level = self.min_level.and_then(rank).unwrap_or(0);
+        let (min_level, submodule_min) = match self.directives {
+            Some(directives) => {
+                let (default, submodules) = directives.resolve(&severity);
+                (default.unwrap_or(min_level), submodules)
+            }
+            None => (min_level, HashMap::new()),
+        };
+
+        if let Target::Split(level) = self.target
+            && !severity.contains_key(level)
+        {
+            eprintln!("rgb_log: SPLIT LEVEL '{level}' IS NOT PART OF THE SEVERITY ORDER");
+        }
+
+        let colored = self.color.enabled_for(&self.target);
+        let clock = self.clock.unwrap_or(Box::new(SystemClock));
+
+        if self.timestamp.render(clock.now(), self.time_zone).is_none()
+            && self.timestamp != Timestamp::None
+        {
+            eprintln!(
+                "rgb_log: TIMESTAMP FORMAT {:?} CAN NOT BE FORMATTED",
+                self.timestamp
+            );
+        }
+
+        let target = (!self.without_console).then_some(self.target);
+        let console = self.console.resolve(target, self.format, colored, rank);
+        let sinks = self
+            .buffer
+            .map(|bfr| (bfr, Sink::default()))
+            .into_iter()
+            .chain(self.sinks)
+            .map(|(mut bfr, sink)| {
+                bfr.set_severity(&severity);
+                let colored = bfr.colored();
+                sink.resolve(bfr, self.format, colored, rank)
+            })
+            .collect();
+
+        super::Log::new_raw(
+            console,
+            sinks,
+            program_name,
+            submodule_names,
+            levels,
+            LevelFilter::new(severity, min_level, submodule_min),
+            self.timestamp,
+            self.time_zone,
+            self.elapsed,
+            clock,
+        )
     }
 }
 
 impl super::Log {
+    #[allow(clippy::too_many_arguments)]
     fn new_raw<I>(
-        buf: Box<dyn LogBuffer>,
+        console: Output<Option<Target>>,
+        sinks: Vec<Output<Box<dyn LogBuffer>>>,
         program_name: Option<String>,
         submodule_pad: PadLeft<'static>,
         levels: I,
+        filter: LevelFilter,
+        timestamp: Timestamp,
+        time_zone: TimeZone,
+        elapsed: Option<Elapsed>,
+        clock: Box<dyn Clock>,
     ) -> Arc<Self>
     where
         I: IntoIterator<Item = (&'static str, StyledContent<&'static str>)> + Clone,
     {
         let level_names = levels.clone().into_iter().map(|(s, _)| s);
+        let start = clock.instant();
 
         Self {
-            buf,
+            console,
+            sinks,
             program_name,
             submodule_pad,
             level_pad: PadLeft::new(level_names),
-            level_color: levels.into_iter().collect(),
+            level_color: levels
+                .into_iter()
+                .map(|(level, styled)| (level, color::ansi(&styled)))
+                .collect(),
+            filter,
+            timestamp,
+            time_zone,
+            elapsed,
+            clock,
+            start,
+            last: start.into(),
         }
         .into()
     }
@@ -0,0 +1,87 @@
+use std::{
+    sync::{Arc, Mutex},
+    time::{Duration, Instant, SystemTime},
+};
+
+/// Source of the timestamp and elapsed column of lines, see LogBuilder::with_clock
+pub trait Clock: 'static + Send + Sync {
+    fn now(&self) -> SystemTime;
+
+    /// Monotonic time for the elapsed column
+    fn instant(&self) -> Instant;
+}
+
+/// The clock of the system, used by default
+#[derive(Clone, Copy, Default, Debug)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> SystemTime {
+        SystemTime::now()
+    }
+
+    fn instant(&self) -> Instant {
+        Instant::now()
+    }
+}
+
+/// Always tells the same time, so the elapsed column stays at zero
+#[derive(Clone, Copy, Debug)]
+pub struct FixedClock {
+    time: SystemTime,
+    instant: Instant,
+}
+
+impl FixedClock {
+    pub fn new(time: SystemTime) -> Self {
+        Self {
+            time,
+            instant: Instant::now(),
+        }
+    }
+}
+
+impl Clock for FixedClock {
+    fn now(&self) -> SystemTime {
+        self.time
+    }
+
+    fn instant(&self) -> Instant {
+        self.instant
+    }
+}
+
+/// Stands still until advanced. Clones share the time,
+/// so keep one to advance the clock given to LogBuilder::with_clock.
+#[derive(Clone, Debug)]
+pub struct ManualClock {
+    start: FixedClock,
+    advanced: Arc<Mutex<Duration>>,
+}
+
+impl ManualClock {
+    pub fn new(time: SystemTime) -> Self {
+        Self {
+            start: FixedClock::new(time),
+            advanced: Default::default(),
+        }
+    }
+
+    pub fn advance(&self, by: Duration) {
+        *self.advanced.lock().unwrap_or_else(|e| e.into_inner()) += by;
+    }
+
+    fn advanced(&self) -> Duration {
+        *self.advanced.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl Clock for ManualClock {
+    fn now(&self) -> SystemTime {
+        self.start.now() + self.advanced()
+    }
+
+    fn instant(&self) -> Instant {
+        self.start.instant() + self.advanced()
+    }
+}
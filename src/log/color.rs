@@ -1,4 +1,98 @@
-use crossterm::style::{self, StyledContent, Stylize, style};
+use crossterm::style::{self, Attribute, StyledContent, Stylize, style};
+
+use crate::log::target::Target;
+
+/// Whether the level column is colored
+#[derive(Clone, Copy, Default, Debug, PartialEq)]
+pub enum ColorMode {
+    /// Colors when the Target is a terminal.
+    /// A non-empty NO_COLOR disables colors, CLICOLOR_FORCE other than 0 enables them.
+    #[default]
+    Auto,
+    /// Colors even when NO_COLOR is set. Only affects the Log it is given to.
+    Always,
+    Never,
+}
+
+impl ColorMode {
+    /// Whether lines written to stdout are colored
+    pub fn enabled(&self) -> bool {
+        self.enabled_for(&Target::Stdout)
+    }
+
+    pub fn enabled_for(&self, target: &Target) -> bool {
+        match self {
+            Self::Always => true,
+            Self::Never => false,
+            Self::Auto => {
+                if std::env::var_os("NO_COLOR").is_some_and(|v| !v.is_empty()) {
+                    false
+                } else if std::env::var_os("CLICOLOR_FORCE").is_some_and(|v| v != "0") {
+                    true
+                } else {
+                    target.is_terminal()
+                }
+            }
+        }
+    }
+}
+
+/// The level wrapped in the escape codes of its style.
+/// Written without the Display of crossterm, which drops colors while NO_COLOR is set
+/// unless they are forced for the whole process, so that ColorMode stays up to each Log.
+pub(crate) fn ansi(styled: &StyledContent<&'static str>) -> String {
+    let style = styled.style();
+
+    let colors = [
+        ("38", style.foreground_color),
+        ("48", style.background_color),
+        ("58", style.underline_color),
+    ];
+    let mut codes = colors
+        .into_iter()
+        .filter_map(|(kind, color)| Some(format!("{kind};{}", color_code(color?)?)))
+        .collect::<Vec<_>>();
+    codes.extend(
+        Attribute::iterator()
+            .filter(|attribute| style.attributes.has(*attribute))
+            .map(Attribute::sgr),
+    );
+
+    if codes.is_empty() {
+        return styled.content().to_string();
+    }
+
+    format!("\x1b[{}m{}\x1b[0m", codes.join(";"), styled.content())
+}
+
+/// Same codes as crossterm uses
+fn color_code(color: style::Color) -> Option<String> {
+    use style::Color::*;
+
+    let code = match color {
+        Black => "5;0".to_string(),
+        DarkGrey => "5;8".to_string(),
+        Red => "5;9".to_string(),
+        DarkRed => "5;1".to_string(),
+        Green => "5;10".to_string(),
+        DarkGreen => "5;2".to_string(),
+        Yellow => "5;11".to_string(),
+        DarkYellow => "5;3".to_string(),
+        Blue => "5;12".to_string(),
+        DarkBlue => "5;4".to_string(),
+        Magenta => "5;13".to_string(),
+        DarkMagenta => "5;5".to_string(),
+        Cyan => "5;14".to_string(),
+        DarkCyan => "5;6".to_string(),
+        White => "5;15".to_string(),
+        Grey => "5;7".to_string(),
+        Rgb { r, g, b } => format!("2;{r};{g};{b}"),
+        AnsiValue(value) => format!("5;{value}"),
+        Reset => return None,
+    };
+
+    Some(code)
+}
 
 pub trait GetColor: 'static {
     fn get_colored_str(&self) -> StyledContent<&'static str>;
@@ -48,3 +142,11 @@ impl GetColor for Color {
         }
     }
 }
+
+#[test]
+fn ansi_ignores_no_color() {
+    let styled = Color::Red("ERROR").get_colored_str().bold();
+
+    assert_eq!(ansi(&styled), "\x1b[38;5;9;1mERROR\x1b[0m");
+    assert_eq!(ansi(&style("INFO")), "INFO");
+}
@@ -0,0 +1,88 @@
+use std::fmt::Display;
+
+/// Value of a key=value pair attached to a line with Print::field
+#[derive(Clone, Debug, PartialEq)]
+pub enum Value {
+    Str(String),
+    Int(i64),
+    Uint(u64),
+    Float(f64),
+    Bool(bool),
+}
+
+impl Value {
+    /// Strings which would be ambiguous as a bare key=value pair
+    pub fn needs_quotes(s: &str) -> bool {
+        s.is_empty()
+            || s.chars()
+                .any(|c| c.is_whitespace() || c.is_control() || c == '"' || c == '=')
+    }
+}
+
+/// Renders strings in quotes only when needed, see Value::needs_quotes
+impl Display for Value {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Value::Str(s) if Value::needs_quotes(s) => write!(f, "{s:?}"),
+            Value::Str(s) => write!(f, "{s}"),
+            Value::Int(n) => write!(f, "{n}"),
+            Value::Uint(n) => write!(f, "{n}"),
+            Value::Float(n) => write!(f, "{n}"),
+            Value::Bool(b) => write!(f, "{b}"),
+        }
+    }
+}
+
+macro_rules! impl_from {
+    ($variant:ident as $inner:ty: $($t:ty),+) => {
+        $(
+            impl From<$t> for Value {
+                fn from(value: $t) -> Self {
+                    Value::$variant(value as $inner)
+                }
+            }
+        )+
+    };
+}
+
+impl_from!(Int as i64: i8, i16, i32, i64, isize);
+impl_from!(Uint as u64: u8, u16, u32, u64, usize);
+impl_from!(Float as f64: f32, f64);
+
+impl From<bool> for Value {
+    fn from(value: bool) -> Self {
+        Value::Bool(value)
+    }
+}
+
+impl From<String> for Value {
+    fn from(value: String) -> Self {
+        Value::Str(value)
+    }
+}
+
+impl From<&str> for Value {
+    fn from(value: &str) -> Self {
+        Value::Str(value.to_string())
+    }
+}
+
+impl From<&String> for Value {
+    fn from(value: &String) -> Self {
+        Value::Str(value.clone())
+    }
+}
+
+#[test]
+fn display_quotes_ambiguous_strings() {
+    let rendered = [
+        Value::from("plain"),
+        Value::from("two words"),
+        Value::from(""),
+        Value::from(-42),
+        Value::from(true),
+    ]
+    .map(|v| v.to_string());
+
+    assert_eq!(rendered, ["plain", "\"two words\"", "\"\"", "-42", "true"]);
+}
@@ -0,0 +1,147 @@
+use std::{
+    collections::HashMap,
+    sync::atomic::{AtomicU8, Ordering},
+};
+
+/// Environment variable read by LogBuilder::with_env_filter when no other name is given
+pub const DEFAULT_FILTER_ENV: &str = "RGB_LOG";
+
+/// Level name which filters out every level
+pub const OFF: &str = "off";
+
+/// Decides which levels get printed, see Log::enabled_for
+pub(crate) struct LevelFilter {
+    severity: HashMap<&'static str, u8>,
+    min_level: AtomicU8,
+    submodule_min: HashMap<String, u8>,
+}
+
+impl LevelFilter {
+    pub fn new(
+        severity: HashMap<&'static str, u8>,
+        min_level: u8,
+        submodule_min: HashMap<String, u8>,
+    ) -> Self {
+        Self {
+            severity,
+            min_level: AtomicU8::new(min_level),
+            submodule_min,
+        }
+    }
+
+    pub fn set_min_level(&self, level: &str) -> bool {
+        match self.severity.get(level) {
+            Some(rank) => {
+                self.min_level.store(*rank, Ordering::Relaxed);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// None for levels missing from the severity order
+    pub fn rank(&self, level: &str) -> Option<u8> {
+        self.severity.get(level).copied()
+    }
+
+    pub fn enabled(&self, submod: Option<&str>, level: &str) -> bool {
+        let Some(rank) = self.severity.get(level) else {
+            return true;
+        };
+
+        let min = submod
+            .and_then(|submod| self.submodule_min.get(submod))
+            .copied()
+            .unwrap_or_else(|| self.min_level.load(Ordering::Relaxed));
+
+        *rank >= min
+    }
+}
+
+/// Minimum levels parsed from a comma separated list of directives,
+/// e.g. `info,very_long_subject=debug`.
+///
+/// A bare level sets the threshold for every submodule without a directive of its own.
+/// Level names are matched case-insensitively.
+#[derive(Default, Clone, Debug, PartialEq)]
+pub struct Directives {
+    pub default: Option<String>,
+    pub submodules: Vec<(String, String)>,
+}
+
+impl Directives {
+    pub fn parse(s: &str) -> Self {
+        let mut directives = Self::default();
+
+        for directive in s.split(',').map(str::trim).filter(|d| !d.is_empty()) {
+            match directive.split_once('=') {
+                Some((submod, level)) => directives
+                    .submodules
+                    .push((submod.trim().to_string(), level.trim().to_string())),
+                None => {
+                    let _ = directives.default.insert(directive.to_string());
+                }
+            }
+        }
+
+        directives
+    }
+
+    pub fn from_env(var: &str) -> Option<Self> {
+        std::env::var(var).ok().map(|s| Self::parse(&s))
+    }
+
+    /// Looks up the ranks of the parsed level names.
+    /// Unknown level names are reported and skipped.
+    pub(crate) fn resolve(
+        &self,
+        severity: &HashMap<&'static str, u8>,
+    ) -> (Option<u8>, HashMap<String, u8>) {
+        let default = self
+            .default
+            .as_deref()
+            .and_then(|level| resolve_level(severity, level));
+
+        let submodules = self
+            .submodules
+            .iter()
+            .filter_map(|(submod, level)| {
+                resolve_level(severity, level).map(|rank| (submod.clone(), rank))
+            })
+            .collect();
+
+        (default, submodules)
+    }
+}
+
+fn resolve_level(severity: &HashMap<&'static str, u8>, level: &str) -> Option<u8> {
+    if level.eq_ignore_ascii_case(OFF) {
+        return Some(u8::MAX);
+    }
+
+    let rank = severity
+        .iter()
+        .find(|(name, _)| name.eq_ignore_ascii_case(level))
+        .map(|(_, rank)| *rank);
+
+    if rank.is_none() {
+        eprintln!("rgb_log: UNKNOWN LEVEL '{level}' IN FILTER DIRECTIVE");
+    }
+
+    rank
+}
+
+#[test]
+fn parse_directives() {
+    let directives = Directives::parse("info, very_long_subject=debug,,ghost_mod = off");
+
+    let expected = Directives {
+        default: Some("info".to_string()),
+        submodules: vec![
+            ("very_long_subject".to_string(), "debug".to_string()),
+            ("ghost_mod".to_string(), "off".to_string()),
+        ],
+    };
+
+    assert_eq!(directives, expected);
+}
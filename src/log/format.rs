@@ -0,0 +1,194 @@
+use crate::log::{LogRecord, field::Value, timestamp};
+
+/// How Print renders a line, for the terminal and LogBuffer alike
+#[derive(Clone, Copy, Default, Debug, PartialEq)]
+pub enum Format {
+    /// Padded and colored columns
+    #[default]
+    Human,
+    /// One JSON object per line, without any escape codes:
+    /// `{"ts":..,"elapsed":..,"program":..,"submodule":..,"level":..,"msg":..,"fields":{..}}`
+    ///
+    /// `ts` is left out with Timestamp::None, `elapsed` without an elapsed column,
+    /// `fields` when there are none.
+    Json,
+    /// `ts=.. elapsed=0.153s level=info program=.. submodule=main msg=".." key=value`
+    ///
+    /// Values are quoted when they contain whitespace, quotes or `=`.
+    /// Missing timestamp, program name and submodule are left out.
+    Logfmt,
+}
+
+pub(crate) fn json(record: &LogRecord, timestamp: Option<&str>) -> String {
+    let mut line = String::from("{");
+
+    if let Some(timestamp) = timestamp {
+        line.push_str("\"ts\":");
+        push_json_str(&mut line, timestamp);
+        line.push(',');
+    }
+    if let Some(elapsed) = record.elapsed {
+        line.push_str("\"elapsed\":");
+        line.push_str(&timestamp::secs(elapsed));
+        line.push(',');
+    }
+
+    line.push_str("\"program\":");
+    push_json_opt_str(&mut line, record.program.as_deref());
+    line.push_str(",\"submodule\":");
+    push_json_opt_str(&mut line, record.submodule.as_deref());
+    line.push_str(",\"level\":");
+    push_json_str(&mut line, &record.level);
+    line.push_str(",\"msg\":");
+    push_json_str(&mut line, &record.message);
+
+    if !record.fields.is_empty() {
+        line.push_str(",\"fields\":{");
+        for (i, (key, value)) in record.fields.iter().enumerate() {
+            if i > 0 {
+                line.push(',');
+            }
+            push_json_str(&mut line, key);
+            line.push(':');
+            push_json_value(&mut line, value);
+        }
+        line.push('}');
+    }
+
+    line.push_str("}\r\n");
+    line
+}
+
+pub(crate) fn logfmt(record: &LogRecord, timestamp: Option<&str>) -> String {
+    let mut line = String::new();
+
+    if let Some(timestamp) = timestamp {
+        push_logfmt_pair(&mut line, "ts", timestamp);
+    }
+    if let Some(elapsed) = record.elapsed {
+        push_logfmt_pair(
+            &mut line,
+            "elapsed",
+            &format!("{}s", timestamp::secs(elapsed)),
+        );
+    }
+    push_logfmt_pair(&mut line, "level", &record.level.to_lowercase());
+    if let Some(ref program) = record.program {
+        push_logfmt_pair(&mut line, "program", program);
+    }
+    if let Some(ref submod) = record.submodule {
+        push_logfmt_pair(&mut line, "submodule", submod);
+    }
+    push_logfmt_pair(&mut line, "msg", &record.message);
+
+    for (key, value) in record.fields.iter() {
+        match value {
+            Value::Str(s) => push_logfmt_pair(&mut line, key, s),
+            _ => push_logfmt_pair(&mut line, key, &value.to_string()),
+        }
+    }
+
+    line.push_str("\r\n");
+    line
+}
+
+fn push_logfmt_pair(out: &mut String, key: &str, value: &str) {
+    if !out.is_empty() {
+        out.push(' ');
+    }
+    // Keys can't be quoted, so what would end them becomes _
+    out.extend(key.chars().map(|c| match c {
+        '=' | '"' => '_',
+        c if c.is_whitespace() => '_',
+        c => c,
+    }));
+    out.push('=');
+
+    if !Value::needs_quotes(value) {
+        out.push_str(value);
+        return;
+    }
+
+    out.push('"');
+    push_escaped(out, value);
+    out.push('"');
+}
+
+fn push_json_value(out: &mut String, value: &Value) {
+    match value {
+        Value::Str(s) => push_json_str(out, s),
+        // JSON has no representation for NaN and the infinities
+        Value::Float(n) if !n.is_finite() => out.push_str("null"),
+        Value::Int(_) | Value::Uint(_) | Value::Float(_) | Value::Bool(_) => {
+            out.push_str(&value.to_string())
+        }
+    }
+}
+
+fn push_json_opt_str(out: &mut String, s: Option<&str>) {
+    match s {
+        Some(s) => push_json_str(out, s),
+        None => out.push_str("null"),
+    }
+}
+
+fn push_json_str(out: &mut String, s: &str) {
+    out.push('"');
+    push_escaped(out, s);
+    out.push('"');
+}
+
+/// Backslash escapes shared by JSON strings and quoted logfmt values
+fn push_escaped(out: &mut String, s: &str) {
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if c.is_control() => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+}
+
+#[test]
+fn json_escapes_and_types() {
+    let record = LogRecord::new(
+        Some("app"),
+        None,
+        "INFO",
+        "say \"hi\"\n\u{1b}",
+        [("id", Value::from(7)), ("ok", Value::from(true))],
+    );
+    let line = json(&record, None);
+
+    let expected = concat!(
+        r#"{"program":"app","submodule":null,"level":"INFO","msg":"say \"hi\"\n\u001b","#,
+        r#""fields":{"id":7,"ok":true}}"#,
+        "\r\n"
+    );
+
+    assert_eq!(line, expected);
+}
+
+#[test]
+fn logfmt_quotes_when_needed() {
+    let record = LogRecord::new(
+        None,
+        Some("main"),
+        "INFO",
+        "user said \"hi\"",
+        [
+            ("id", Value::from(7)),
+            ("name", Value::from("a=b")),
+            ("user \"full\" name=", Value::from("x")),
+        ],
+    );
+    let line = logfmt(&record, None);
+
+    let expected = "level=info submodule=main msg=\"user said \\\"hi\\\"\" id=7 name=\"a=b\" user__full__name_=x\r\n";
+
+    assert_eq!(line, expected);
+}
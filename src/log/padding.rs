@@ -82,7 +82,7 @@ fn default_to_pad_map_left() {
         .1
         .into_iter()
         .collect::<Vec<(&str, u8)>>();
-    v.sort_by(|(a, _), (b, _)| a.cmp(b));
+    v.sort_by_key(|(k, _)| *k);
 
     let expected = vec![("ERROR", 0), ("INFO", 1)];
 
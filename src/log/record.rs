@@ -0,0 +1,110 @@
+use std::time::{Duration, SystemTime};
+
+use crate::log::{
+    field::Value,
+    timestamp::{self, TimeZone, Timestamp},
+};
+
+/// Parts of a line, handed to LogBuffer::push_record
+#[derive(Clone, Debug)]
+pub struct LogRecord {
+    pub timestamp: SystemTime,
+    /// Set when Log has an elapsed column, see LogBuilder::with_elapsed
+    pub elapsed: Option<Duration>,
+    pub program: Option<String>,
+    pub submodule: Option<String>,
+    pub level: String,
+    pub message: String,
+    pub fields: Vec<(String, Value)>,
+    pub(crate) line: String,
+}
+
+impl LogRecord {
+    /// Timestamped now. Only Log knows how to render the line with its padding and format,
+    /// see into_line for what is pushed otherwise.
+    pub fn new<I, K>(
+        program: Option<&str>,
+        submodule: Option<&str>,
+        level: &str,
+        message: &str,
+        fields: I,
+    ) -> Self
+    where
+        I: IntoIterator<Item = (K, Value)>,
+        K: ToString,
+    {
+        Self {
+            timestamp: SystemTime::now(),
+            elapsed: None,
+            program: program.map(str::to_string),
+            submodule: submodule.map(str::to_string),
+            level: level.to_string(),
+            message: message.to_string(),
+            fields: fields
+                .into_iter()
+                .map(|(key, value)| (key.to_string(), value))
+                .collect(),
+            line: String::new(),
+        }
+    }
+
+    /// The record as rendered by Log for the buffer, empty for records made with new
+    pub fn line(&self) -> &str {
+        &self.line
+    }
+
+    /// Falls back to an unpadded Human line when the record was not rendered by Log
+    pub fn into_line(self) -> String {
+        if self.line.is_empty() {
+            self.render_plain()
+        } else {
+            self.line
+        }
+    }
+
+    fn render_plain(&self) -> String {
+        let mut line = String::new();
+
+        let stamp = Timestamp::default();
+        if let Some(ts) = stamp.render(self.timestamp, TimeZone::Utc) {
+            line.push_str(&stamp.human(&ts));
+            line.push(' ');
+        }
+        if let Some(elapsed) = self.elapsed {
+            line.push_str(&timestamp::human_elapsed(elapsed));
+            line.push(' ');
+        }
+
+        match (&self.program, &self.submodule) {
+            (Some(program), Some(submod)) => line.push_str(&format!("[{program} {submod}] ")),
+            (Some(name), None) | (None, Some(name)) => line.push_str(&format!("[{name}] ")),
+            (None, None) => (),
+        }
+
+        line.push_str(&format!("{}: {}", self.level, self.message));
+        for (key, value) in self.fields.iter() {
+            line.push_str(&format!(" {key}={value}"));
+        }
+
+        line.push_str("\r\n");
+        line
+    }
+}
+
+#[test]
+fn into_line_renders_unrendered_records() {
+    let record = LogRecord::new(
+        Some("app"),
+        Some("db"),
+        "INFO",
+        "hello",
+        [("user", Value::from("ferris"))],
+    );
+
+    assert!(record.line().is_empty());
+    assert!(
+        record
+            .into_line()
+            .ends_with("[app db] INFO: hello user=ferris\r\n")
+    );
+}
@@ -0,0 +1,64 @@
+use crate::log::format::Format;
+
+/// Threshold and format of one output of Log, see LogBuilder::with_sink and with_console_sink.
+/// Options left out follow the Log.
+///
+/// The filter of the Log applies first, so a sink only narrows down the levels it receives.
+#[derive(Clone, Copy, Default, Debug)]
+pub struct Sink {
+    pub(crate) min_level: Option<&'static str>,
+    pub(crate) format: Option<Format>,
+}
+
+impl Sink {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Levels less severe than this one are not sent to the sink
+    pub fn with_min_level(mut self, level: &'static str) -> Self {
+        let _ = self.min_level.insert(level);
+        self
+    }
+
+    pub fn with_format(mut self, format: Format) -> Self {
+        let _ = self.format.insert(format);
+        self
+    }
+
+    /// Fills in the options left out from the Log
+    pub(crate) fn resolve<T>(
+        self,
+        target: T,
+        format: Format,
+        colored: bool,
+        rank: impl Fn(&str) -> Option<u8>,
+    ) -> Output<T> {
+        let format = self.format.unwrap_or(format);
+        Output {
+            target,
+            min_level: self.min_level.and_then(rank),
+            format,
+            colored: colored && format == Format::Human,
+        }
+    }
+}
+
+/// A Sink resolved against the severity order and format of its Log
+pub(crate) struct Output<T> {
+    pub target: T,
+    pub min_level: Option<u8>,
+    pub format: Format,
+    /// Only ever true for the Human format
+    pub colored: bool,
+}
+
+impl<T> Output<T> {
+    /// Takes the rank of the level, None for levels missing from the severity order
+    pub fn passes(&self, rank: Option<u8>) -> bool {
+        match (self.min_level, rank) {
+            (Some(min), Some(rank)) => rank >= min,
+            _ => true,
+        }
+    }
+}
@@ -0,0 +1,49 @@
+use std::{
+    io::{IsTerminal, Write},
+    sync::Mutex,
+};
+
+/// Where the console lines of Log are written to
+#[derive(Default)]
+pub enum Target {
+    #[default]
+    Stdout,
+    Stderr,
+    /// Lines of this level and more severe ones go to stderr, the rest to stdout.
+    /// Levels missing from the severity order go to stdout.
+    Split(&'static str),
+    /// See Target::writer
+    Writer(Mutex<Box<dyn Write + Send>>),
+}
+
+impl Target {
+    /// Any writer, e.g. a file or a buffer shared with a test
+    pub fn writer(w: impl Write + Send + 'static) -> Self {
+        Self::Writer(Mutex::new(Box::new(w)))
+    }
+
+    /// Whether all lines end up on a terminal, as ColorMode::Auto asks
+    pub fn is_terminal(&self) -> bool {
+        match self {
+            Self::Stdout => std::io::stdout().is_terminal(),
+            Self::Stderr => std::io::stderr().is_terminal(),
+            Self::Split(_) => std::io::stdout().is_terminal() && std::io::stderr().is_terminal(),
+            Self::Writer(_) => false,
+        }
+    }
+
+    /// Writes the line while holding the lock of the output, so lines of threads don't interleave.
+    /// Write errors are ignored, there is nowhere left to report them.
+    pub(crate) fn write(&self, line: &str, to_stderr: bool) {
+        let _ = match self {
+            Self::Stdout => std::io::stdout().lock().write_all(line.as_bytes()),
+            Self::Stderr => std::io::stderr().lock().write_all(line.as_bytes()),
+            Self::Split(_) if to_stderr => std::io::stderr().lock().write_all(line.as_bytes()),
+            Self::Split(_) => std::io::stdout().lock().write_all(line.as_bytes()),
+            Self::Writer(w) => match w.lock() {
+                Ok(mut w) => w.write_all(line.as_bytes()).and_then(|_| w.flush()),
+                Err(_) => Ok(()),
+            },
+        };
+    }
+}
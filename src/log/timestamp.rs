@@ -0,0 +1,159 @@
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// How lines are timestamped, see LogBuilder::with_timestamp
+#[derive(Clone, Debug, PartialEq)]
+pub enum Timestamp {
+    /// `2025-07-01T12:00:00Z`, shown as `2025-07-01 12:00:00` by the Human format
+    /// unless the rfc feature is enabled
+    #[cfg(feature = "chrono")]
+    Rfc3339(Precision),
+    /// Pattern as understood by chrono, e.g. `%H:%M:%S%.3f`
+    #[cfg(feature = "chrono")]
+    Strftime(String),
+    /// Seconds since the Unix epoch, e.g. `1751371200.153`
+    Unix(Precision),
+    None,
+}
+
+/// Digits of a second
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Precision {
+    Seconds,
+    Millis,
+    Micros,
+}
+
+/// Time zone of Timestamp::Rfc3339 and Timestamp::Strftime
+#[derive(Clone, Copy, Default, Debug, PartialEq)]
+pub enum TimeZone {
+    #[default]
+    Utc,
+    #[cfg(feature = "chrono")]
+    Local,
+    #[cfg(feature = "chrono")]
+    Offset(chrono::FixedOffset),
+}
+
+/// Column of monotonic time next to the timestamp, see LogBuilder::with_elapsed
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Elapsed {
+    /// Time since LogBuilder::build, e.g. `+0.153s`
+    SinceStart,
+    /// Time since the previous line that passed the filter of Log
+    SinceLast,
+}
+
+impl Default for Precision {
+    /// Chosen by the seconds/milliseconds features
+    fn default() -> Self {
+        if cfg!(feature = "milliseconds") {
+            Self::Millis
+        } else {
+            Self::Seconds
+        }
+    }
+}
+
+impl Default for Timestamp {
+    /// RFC 3339 with the chrono feature, no timestamp without
+    fn default() -> Self {
+        #[cfg(feature = "chrono")]
+        return Self::Rfc3339(Precision::default());
+
+        #[cfg(not(feature = "chrono"))]
+        Self::None
+    }
+}
+
+impl Timestamp {
+    /// None for Timestamp::None, and for strftime patterns chrono can't format
+    #[allow(unused_variables)]
+    pub(crate) fn render(&self, time: SystemTime, zone: TimeZone) -> Option<String> {
+        match self {
+            #[cfg(feature = "chrono")]
+            Self::Rfc3339(precision) => {
+                use chrono::{DateTime, SecondsFormat, Utc};
+
+                let precision = match precision {
+                    Precision::Seconds => SecondsFormat::Secs,
+                    Precision::Millis => SecondsFormat::Millis,
+                    Precision::Micros => SecondsFormat::Micros,
+                };
+
+                let time = DateTime::<Utc>::from(time);
+                Some(match zone {
+                    TimeZone::Utc => time.to_rfc3339_opts(precision, true),
+                    TimeZone::Local => time
+                        .with_timezone(&chrono::Local)
+                        .to_rfc3339_opts(precision, false),
+                    TimeZone::Offset(offset) => time
+                        .with_timezone(&offset)
+                        .to_rfc3339_opts(precision, false),
+                })
+            }
+            #[cfg(feature = "chrono")]
+            Self::Strftime(pattern) => {
+                use chrono::{DateTime, Utc};
+                use std::fmt::Write;
+
+                let time = DateTime::<Utc>::from(time);
+                let mut timestamp = String::new();
+                // Formatting fails instead of panicking with write!
+                let written = match zone {
+                    TimeZone::Utc => write!(timestamp, "{}", time.format(pattern)),
+                    TimeZone::Local => write!(
+                        timestamp,
+                        "{}",
+                        time.with_timezone(&chrono::Local).format(pattern)
+                    ),
+                    TimeZone::Offset(offset) => {
+                        write!(timestamp, "{}", time.with_timezone(&offset).format(pattern))
+                    }
+                };
+                written.ok().map(|_| timestamp)
+            }
+            Self::Unix(precision) => {
+                let since_epoch = time.duration_since(UNIX_EPOCH).unwrap_or_default();
+                let secs = since_epoch.as_secs();
+                Some(match precision {
+                    Precision::Seconds => secs.to_string(),
+                    Precision::Millis => format!("{secs}.{:03}", since_epoch.subsec_millis()),
+                    Precision::Micros => format!("{secs}.{:06}", since_epoch.subsec_micros()),
+                })
+            }
+            Self::None => None,
+        }
+    }
+
+    /// Drops the T and Z of RFC 3339 timestamps unless the rfc feature is enabled
+    pub(crate) fn human(&self, timestamp: &str) -> String {
+        match self {
+            #[cfg(feature = "chrono")]
+            Self::Rfc3339(_) if !cfg!(feature = "rfc") => {
+                timestamp.replace("T", " ").replace("Z", "")
+            }
+            _ => timestamp.to_string(),
+        }
+    }
+}
+
+/// `+0.153s`, for the Human format
+pub(crate) fn human_elapsed(elapsed: Duration) -> String {
+    format!("+{}s", secs(elapsed))
+}
+
+/// `0.153`
+pub(crate) fn secs(elapsed: Duration) -> String {
+    format!("{:.3}", elapsed.as_secs_f64())
+}
+
+#[test]
+fn unix_precision() {
+    let time = UNIX_EPOCH + std::time::Duration::from_micros(1_500_250);
+
+    let unix = |precision| Timestamp::Unix(precision).render(time, TimeZone::Utc);
+
+    assert_eq!(unix(Precision::Seconds).as_deref(), Some("1"));
+    assert_eq!(unix(Precision::Millis).as_deref(), Some("1.500"));
+    assert_eq!(unix(Precision::Micros).as_deref(), Some("1.500250"));
+}
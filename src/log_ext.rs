@@ -0,0 +1,90 @@
+use std::sync::Arc;
+
+use ::log::{Level, LevelFilter, Metadata, Record, SetLoggerError};
+
+use crate::{
+    level_map::LevelMap,
+    log::{Log, Print},
+};
+
+/// Implements log::Log on top of Log.
+/// The target of a record (its module path unless overridden) is used as the submodule.
+pub struct LogAdapter {
+    log: Arc<Log>,
+    levels: LevelMap,
+}
+
+impl LogAdapter {
+    /// Levels are mapped by LevelMap::default
+    pub fn new(log: Arc<Log>) -> Self {
+        Self {
+            log,
+            levels: LevelMap::default(),
+        }
+    }
+
+    /// Prints records of the facade level as the given configured level
+    pub fn with_level(mut self, level: Level, name: &'static str) -> Self {
+        self.levels.set(level, name);
+        self
+    }
+
+    /// Installs the adapter as the global logger of the facade.
+    /// Filtering is left to Log, so the max level of the facade is set to Trace.
+    pub fn init(self) -> Result<(), SetLoggerError> {
+        ::log::set_boxed_logger(Box::new(self))?;
+        ::log::set_max_level(LevelFilter::Trace);
+        Ok(())
+    }
+}
+
+/// Shorthand for LogAdapter::new(log).init()
+pub fn init(log: Arc<Log>) -> Result<(), SetLoggerError> {
+    LogAdapter::new(log).init()
+}
+
+impl ::log::Log for LogAdapter {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        self.log
+            .enabled_for(Some(metadata.target()), self.levels.get(metadata.level()))
+    }
+
+    fn log(&self, record: &Record) {
+        let level = self.levels.get(record.level());
+
+        Print::new(
+            &self.log,
+            (Some(record.target()), Some(level), Some(record.args())),
+        )
+        .print();
+    }
+
+    fn flush(&self) {}
+}
+
+#[test]
+fn prints_records_with_target_as_submodule() {
+    use ::log::Log as _;
+
+    let capture = crate::log::Capture::default();
+    let log = Log::builder()
+        .with_buffer(capture.clone())
+        .without_console()
+        .build();
+    let adapter = LogAdapter::new(log).with_level(Level::Info, "OK");
+
+    for (level, message) in [(Level::Warn, "careful"), (Level::Info, "done")] {
+        adapter.log(
+            &Record::builder()
+                .level(level)
+                .target("app::db")
+                .args(format_args!("{message}"))
+                .build(),
+        );
+    }
+
+    let lines = capture.0.lock().unwrap();
+    assert!(lines[0].contains(" app::db]"));
+    assert!(lines[0].ends_with("ERROR: careful\r\n"));
+    assert!(lines[1].ends_with("OK: done\r\n"));
+}
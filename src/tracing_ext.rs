@@ -0,0 +1,139 @@
+use std::{fmt::Debug, sync::Arc};
+
+use tracing_core::{
+    Event, Level, Subscriber,
+    field::{Field, Visit},
+};
+use tracing_subscriber::{Layer, layer::Context, registry::LookupSpan};
+
+use crate::{
+    level_map::LevelMap,
+    log::{Log, Print, field::Value},
+};
+
+/// Layer printing events through Log.
+/// The name of the innermost span is used as the submodule, falling back to the target of the
+/// event when it happens outside of any span.
+pub struct LogLayer {
+    log: Arc<Log>,
+    levels: LevelMap,
+}
+
+impl LogLayer {
+    /// Levels are mapped by LevelMap::default
+    pub fn new(log: Arc<Log>) -> Self {
+        Self {
+            log,
+            levels: LevelMap::default(),
+        }
+    }
+
+    /// Prints events of the tracing level as the given configured level
+    pub fn with_level(mut self, level: Level, name: &'static str) -> Self {
+        self.levels.set(&level, name);
+        self
+    }
+}
+
+impl<S> Layer<S> for LogLayer
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    fn on_event(&self, event: &Event<'_>, ctx: Context<'_, S>) {
+        let metadata = event.metadata();
+        let level = self.levels.get(metadata.level());
+        let submod = ctx
+            .event_span(event)
+            .map(|span| span.name())
+            .unwrap_or(metadata.target());
+
+        if !self.log.enabled_for(Some(submod), level) {
+            return;
+        }
+
+        let mut visitor = FieldVisitor::default();
+        event.record(&mut visitor);
+
+        let mut print = Print::new(
+            &self.log,
+            (Some(submod), Some(level), Some(visitor.message)),
+        );
+        for (key, value) in visitor.fields {
+            print.field(key, value);
+        }
+        print.print();
+    }
+}
+
+/// Collects the message of an event, keeping any other fields apart
+#[derive(Default)]
+struct FieldVisitor {
+    message: String,
+    fields: Vec<(&'static str, Value)>,
+}
+
+impl FieldVisitor {
+    fn push_field(&mut self, field: &Field, value: impl Into<Value>) {
+        self.fields.push((field.name(), value.into()));
+    }
+}
+
+impl Visit for FieldVisitor {
+    fn record_i64(&mut self, field: &Field, value: i64) {
+        self.push_field(field, value);
+    }
+
+    fn record_u64(&mut self, field: &Field, value: u64) {
+        self.push_field(field, value);
+    }
+
+    fn record_f64(&mut self, field: &Field, value: f64) {
+        self.push_field(field, value);
+    }
+
+    fn record_bool(&mut self, field: &Field, value: bool) {
+        self.push_field(field, value);
+    }
+
+    fn record_str(&mut self, field: &Field, value: &str) {
+        if field.name() == "message" {
+            self.message = value.to_string();
+        } else {
+            self.push_field(field, value);
+        }
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn Debug) {
+        if field.name() == "message" {
+            self.message = format!("{value:?}");
+        } else {
+            self.push_field(field, format!("{value:?}"));
+        }
+    }
+}
+
+#[test]
+fn prints_events_with_span_as_submodule() {
+    use tracing_subscriber::{Registry, layer::SubscriberExt};
+
+    let capture = crate::log::Capture::default();
+    let log = Log::builder()
+        .with_buffer(capture.clone())
+        .without_console()
+        .build();
+    let subscriber = Registry::default().with(LogLayer::new(log));
+
+    tracing::subscriber::with_default(subscriber, || {
+        tracing::warn!(target: "app", "outside");
+
+        let span = tracing::info_span!("startup");
+        let _enter = span.enter();
+        tracing::trace!(attempt = 2, user = "ferris", "inside");
+    });
+
+    let lines = capture.0.lock().unwrap();
+    assert!(lines[0].contains(" app]"));
+    assert!(lines[0].ends_with("ERROR: outside\r\n"));
+    assert!(lines[1].contains(" startup]"));
+    assert!(lines[1].ends_with("DEBUG: inside attempt=2 user=ferris\r\n"));
+}